@@ -0,0 +1,441 @@
+use super::{Model, Trainer, TrainingConfigError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Version of the checkpoints written now. It goes up whenever the layout of what is saved changes,
+/// checkpoints of the versions before are upgraded while loading.
+/// The seed of a training run drives ChaCha8 from `rand_chacha`, seeded with the seed and the generation,
+/// see `training::rng_for`.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// The first bytes of a binary checkpoint, JSON never starts like this
+const MAGIC: &[u8; 4] = b"SMPC";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointFormat {
+    /// Readable and easy to edit by hand
+    Json,
+    /// Far smaller and faster, the numbers are stored as they are in memory
+    Binary,
+}
+
+impl CheckpointFormat {
+    /// JSON for paths ending in `.json`, otherwise binary
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Binary,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    /// Written by a newer version of the program
+    UnsupportedVersion(u32),
+    /// Neither binary nor JSON with a version
+    NotACheckpoint,
+    /// A heat map of this shape instead of 8 by 8
+    BadHeatMap(Vec<usize>),
+    /// A piece's worth that isn't a finite number
+    BadMaterial(f64),
+    /// A mutation scale that isn't a finite number above 0
+    BadMutationScale(f64),
+    /// A training run without any models
+    EmptyPopulation,
+    /// Settings a trainer can't work with
+    Config(TrainingConfigError),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "checkpoint file: {error}"),
+            Self::Json(error) => write!(f, "JSON checkpoint: {error}"),
+            Self::Binary(error) => write!(f, "binary checkpoint: {error}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "checkpoint version {version} is newer than the supported {CHECKPOINT_VERSION}"
+            ),
+            Self::NotACheckpoint => write!(f, "not a checkpoint"),
+            Self::BadHeatMap(shape) => write!(f, "heat map of shape {shape:?} instead of [8, 8]"),
+            Self::BadMaterial(worth) => write!(f, "piece worth {worth} isn't a finite number"),
+            Self::BadMutationScale(scale) => {
+                write!(f, "mutation scale {scale} isn't a finite number above 0")
+            }
+            Self::EmptyPopulation => write!(f, "training run without any models"),
+            Self::Config(error) => write!(f, "training settings: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+impl From<serde_json::Error> for CheckpointError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+impl From<TrainingConfigError> for CheckpointError {
+    fn from(error: TrainingConfigError) -> Self {
+        Self::Config(error)
+    }
+}
+impl From<bincode::Error> for CheckpointError {
+    fn from(error: bincode::Error) -> Self {
+        Self::Binary(error)
+    }
+}
+
+/// How a JSON checkpoint looks, the version first so it is found before anything else is parsed
+#[derive(Serialize, Deserialize)]
+struct JsonCheckpoint<T> {
+    version: u32,
+    content: T,
+}
+
+fn encode<T: Serialize>(content: &T, format: CheckpointFormat) -> Result<Vec<u8>, CheckpointError> {
+    Ok(match format {
+        CheckpointFormat::Json => serde_json::to_vec_pretty(&JsonCheckpoint {
+            version: CHECKPOINT_VERSION,
+            content,
+        })?,
+        CheckpointFormat::Binary => {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend(CHECKPOINT_VERSION.to_le_bytes());
+            bincode::serialize_into(&mut bytes, content)?;
+            bytes
+        }
+    })
+}
+
+/// Reads a checkpoint in either format, telling them apart by the first bytes
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CheckpointError> {
+    if let Some(rest) = bytes.strip_prefix(MAGIC) {
+        let (version, content) = rest
+            .split_first_chunk::<4>()
+            .ok_or(CheckpointError::NotACheckpoint)?;
+        check_version(u32::from_le_bytes(*version))?;
+        return Ok(bincode::deserialize(content)?);
+    }
+
+    let mut value: serde_json::Value = serde_json::from_slice(bytes)?;
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or(CheckpointError::NotACheckpoint)?;
+    check_version(u32::try_from(version).unwrap_or(u32::MAX))?;
+    let content = value
+        .get_mut("content")
+        .ok_or(CheckpointError::NotACheckpoint)?
+        .take();
+    Ok(serde_json::from_value(content)?)
+}
+
+/// Fails for checkpoints from the future. Older versions need an upgrade here once there are any.
+fn check_version(version: u32) -> Result<(), CheckpointError> {
+    if version > CHECKPOINT_VERSION {
+        return Err(CheckpointError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+impl Model {
+    pub fn to_checkpoint(&self, format: CheckpointFormat) -> Result<Vec<u8>, CheckpointError> {
+        encode(self, format)
+    }
+
+    pub fn from_checkpoint(bytes: &[u8]) -> Result<Self, CheckpointError> {
+        let model: Self = decode(bytes)?;
+        model.check()?;
+        Ok(model)
+    }
+
+    /// Fails for what the format allows but a model can't work with, a hand edited file may have anything
+    fn check(&self) -> Result<(), CheckpointError> {
+        let heat_maps = std::iter::once(&self.heat_maps).chain(&self.black_heat_maps);
+        for heat_map in heat_maps.flatten().flatten() {
+            if heat_map.shape() != [8, 8] {
+                return Err(CheckpointError::BadHeatMap(heat_map.shape().to_vec()));
+            }
+        }
+        if let Some(&worth) = self.material.iter().find(|worth| !worth.is_finite()) {
+            return Err(CheckpointError::BadMaterial(worth));
+        }
+        if !(self.mutation_scale.is_finite() && self.mutation_scale > 0.0) {
+            return Err(CheckpointError::BadMutationScale(self.mutation_scale));
+        }
+        Ok(())
+    }
+
+    /// Writes the model to `path`, as JSON if it ends in `.json`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_checkpoint(CheckpointFormat::for_path(path))?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        Self::from_checkpoint(&std::fs::read(path)?)
+    }
+}
+
+impl Trainer {
+    /// Everything needed to carry on training later: the settings, the current population, how far it got and the seed
+    pub fn to_checkpoint(&self, format: CheckpointFormat) -> Result<Vec<u8>, CheckpointError> {
+        encode(self, format)
+    }
+
+    pub fn from_checkpoint(bytes: &[u8]) -> Result<Self, CheckpointError> {
+        let trainer: Self = decode(bytes)?;
+        trainer.config.validate()?;
+        if trainer.population.is_empty() {
+            return Err(CheckpointError::EmptyPopulation);
+        }
+        for model in trainer.population.iter().chain(&trainer.champion) {
+            model.check()?;
+        }
+        Ok(trainer)
+    }
+
+    /// Writes the training state to `path`, as JSON if it ends in `.json`.
+    /// The file is replaced only once the new one is complete, a crash while saving keeps the old one.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        std::fs::write(
+            &partial,
+            self.to_checkpoint(CheckpointFormat::for_path(path))?,
+        )?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// Picks up a training run saved with `save`, at the generation it got to
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        Self::from_checkpoint(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChessBoard, ChessPiece, Color, GamePhase, Pairing, SearchLimits, SelfPlay};
+    use crate::{MutationError, Selection, TrainingConfig};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn random_model(seed: u64) -> Model {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut model = Model::default();
+        model.randomize_heat_maps(0.0, 1.0, &mut rng);
+        model.mutate_material(0.5, &mut rng);
+        model
+    }
+
+    fn small_trainer() -> Trainer {
+        let config = TrainingConfig {
+            population: 4,
+            pairing: Pairing::Swiss { rounds: 1 },
+            selection: Selection::Roulette,
+            elite: 1,
+            self_play: SelfPlay {
+                board: ChessBoard::from_fen("4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap(),
+                limits: SearchLimits {
+                    depth: Some(1),
+                    ..Default::default()
+                },
+                max_plies: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut trainer = Trainer::with_seed(config, 7).unwrap();
+        trainer.next_generation();
+        trainer
+    }
+
+    fn assert_same_model(a: &Model, b: &Model) {
+        let board = ChessBoard::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        )
+        .unwrap();
+        assert_eq!(a.grade_board(&board), b.grade_board(&board));
+        assert_eq!(a.has_separate_colors(), b.has_separate_colors());
+        for piece in ChessPiece::ALL {
+            assert_eq!(a.get_material_for(piece), b.get_material_for(piece));
+            for color in [Color::White, Color::Black] {
+                for phase in [GamePhase::Middlegame, GamePhase::Endgame] {
+                    assert_eq!(
+                        a.get_heat_map_for(piece, color, phase),
+                        b.get_heat_map_for(piece, color, phase)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn models_round_trip() {
+        let mut separate = random_model(1);
+        separate.separate_colors();
+        separate.randomize_heat_maps(1.0, 2.0, &mut StdRng::seed_from_u64(2));
+        for model in [random_model(3), separate] {
+            for format in [CheckpointFormat::Json, CheckpointFormat::Binary] {
+                let bytes = model.to_checkpoint(format).unwrap();
+                assert_same_model(&Model::from_checkpoint(&bytes).unwrap(), &model);
+            }
+        }
+        // the numbers take 8 bytes each, with little around them
+        let binary = random_model(4)
+            .to_checkpoint(CheckpointFormat::Binary)
+            .unwrap();
+        let json = random_model(4)
+            .to_checkpoint(CheckpointFormat::Json)
+            .unwrap();
+        assert!(binary.len() < 8 * 64 * 12 + 1024, "{}", binary.len());
+        assert!(binary.len() * 2 < json.len());
+    }
+
+    #[test]
+    fn training_resumes() {
+        let mut trainer = small_trainer();
+
+        let path = std::env::temp_dir().join(format!("trainer-{}.json", std::process::id()));
+        trainer.save(&path).unwrap();
+        let mut resumed = Trainer::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(resumed.generation(), 1);
+        assert_eq!(
+            resumed.config().self_play.board.to_fen(),
+            trainer.config().self_play.board.to_fen()
+        );
+        assert_eq!(resumed.config().pairing, Pairing::Swiss { rounds: 1 });
+        for (a, b) in resumed.population().iter().zip(trainer.population()) {
+            assert_same_model(a, b);
+        }
+        assert_same_model(resumed.champion().unwrap(), trainer.champion().unwrap());
+        assert_eq!(resumed.seed(), 7);
+        assert_eq!(resumed.next_generation().generation, 1);
+        assert_eq!(resumed.generation(), 2);
+
+        // the resumed run breeds the same generation as the one that never stopped
+        trainer.next_generation();
+        assert_eq!(
+            resumed.to_checkpoint(CheckpointFormat::Binary).unwrap(),
+            trainer.to_checkpoint(CheckpointFormat::Binary).unwrap()
+        );
+    }
+
+    #[test]
+    fn checks_the_version() {
+        let model = Model::default();
+        let mut binary = model.to_checkpoint(CheckpointFormat::Binary).unwrap();
+        binary[4..8].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Model::from_checkpoint(&binary),
+            Err(CheckpointError::UnsupportedVersion(version)) if version == CHECKPOINT_VERSION + 1
+        ));
+
+        let json = String::from_utf8(model.to_checkpoint(CheckpointFormat::Json).unwrap()).unwrap();
+        let newer = json.replacen(
+            &format!("\"version\": {CHECKPOINT_VERSION}"),
+            &format!("\"version\": {}", CHECKPOINT_VERSION + 1),
+            1,
+        );
+        assert_ne!(json, newer);
+        assert!(matches!(
+            Model::from_checkpoint(newer.as_bytes()),
+            Err(CheckpointError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            Model::from_checkpoint(b"{\"depth\": 4}"),
+            Err(CheckpointError::NotACheckpoint)
+        ));
+    }
+
+    #[test]
+    fn rejects_what_can_not_be_used() {
+        let mut json: serde_json::Value = serde_json::from_slice(
+            &Model::default()
+                .to_checkpoint(CheckpointFormat::Json)
+                .unwrap(),
+        )
+        .unwrap();
+        json["content"]["heat_maps"][0][0] =
+            serde_json::json!({ "v": 1, "dim": [2, 2], "data": [0.0, 0.0, 0.0, 0.0] });
+        assert!(matches!(
+            Model::from_checkpoint(&serde_json::to_vec(&json).unwrap()),
+            Err(CheckpointError::BadHeatMap(shape)) if shape == [2, 2]
+        ));
+        // JSON has no NaN or infinity, the binary format does
+        let bad_models = [
+            Model {
+                mutation_scale: 0.0,
+                ..Model::default()
+            },
+            Model {
+                mutation_scale: f64::NAN,
+                ..Model::default()
+            },
+            Model {
+                mutation_scale: f64::INFINITY,
+                ..Model::default()
+            },
+        ];
+        for model in bad_models {
+            assert!(matches!(
+                Model::from_checkpoint(&model.to_checkpoint(CheckpointFormat::Binary).unwrap()),
+                Err(CheckpointError::BadMutationScale(_))
+            ));
+        }
+        let mut model = Model::default();
+        model.material[4] = f64::NEG_INFINITY;
+        assert!(matches!(
+            Model::from_checkpoint(&model.to_checkpoint(CheckpointFormat::Binary).unwrap()),
+            Err(CheckpointError::BadMaterial(worth)) if worth == f64::NEG_INFINITY
+        ));
+        json["content"]["mutation_scale"] = (-1.0).into();
+        json["content"]["heat_maps"] = serde_json::to_value(&Model::default().heat_maps).unwrap();
+        assert!(matches!(
+            Model::from_checkpoint(&serde_json::to_vec(&json).unwrap()),
+            Err(CheckpointError::BadMutationScale(scale)) if scale == -1.0
+        ));
+
+        let trainer = small_trainer();
+        let mut json: serde_json::Value =
+            serde_json::from_slice(&trainer.to_checkpoint(CheckpointFormat::Json).unwrap())
+                .unwrap();
+        json["content"]["population"] = serde_json::json!([]);
+        assert!(matches!(
+            Trainer::from_checkpoint(&serde_json::to_vec(&json).unwrap()),
+            Err(CheckpointError::EmptyPopulation)
+        ));
+        json["content"]["config"]["mutation"]["probability"] = 1.5.into();
+        assert!(matches!(
+            Trainer::from_checkpoint(&serde_json::to_vec(&json).unwrap()),
+            Err(CheckpointError::Config(TrainingConfigError::Mutation(
+                MutationError::Probability(probability)
+            ))) if probability == 1.5
+        ));
+        json["content"]["config"]["population"] = 0.into();
+        assert!(matches!(
+            Trainer::from_checkpoint(&serde_json::to_vec(&json).unwrap()),
+            Err(CheckpointError::Config(
+                TrainingConfigError::EmptyPopulation
+            ))
+        ));
+    }
+}
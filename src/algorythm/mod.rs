@@ -1,217 +1,434 @@
-use crate::{BoardPosition, ChessBoard, ChessPiece, Color, Piece};
+use crate::{BoardPosition, ChessBoard, ChessPiece, Color, Move};
 use ndarray::{Array2, Zip};
 use rand::Rng;
-use rand_distr::{Distribution, Normal};
-use rayon::prelude::*;
+use rand_distr::{Distribution, Normal, StandardNormal};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
 
-#[derive(Clone, Debug)]
+mod search;
+use search::Search;
+pub use search::{Clock, SearchInfo, SearchLimits, SearchStats};
+
+mod checkpoint;
+pub use checkpoint::{CheckpointError, CheckpointFormat, CHECKPOINT_VERSION};
+
+mod mutation;
+pub use mutation::{HeatMapMutation, MutationError, SelfAdaptation};
+
+mod ordering;
+
+mod self_play;
+pub use self_play::{GameRecord, Outcome, SelfPlay};
+
+mod training;
+pub use training::{
+    GenerationStats, Pairing, Selection, Trainer, TrainingConfig, TrainingConfigError,
+};
+
+mod transposition;
+use transposition::ONE_OFF_TABLE_MB;
+pub use transposition::{Bound, TableEntry, TranspositionTable, DEFAULT_TABLE_MB};
+
+/// Score of a move that checkmates the opponent
+pub const MATE_SCORE: f64 = 1_000_000.0;
+
+/// The phase weights of all pieces at the start added up, knights and bishops count 1, rooks 2 and queens 4
+const OPENING_PHASE: u32 = 24;
+
+/// Which of the two sets of heat maps is meant. The score of a board blends them by how much material is left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamePhase {
+    Middlegame,
+    Endgame,
+}
+
+/// What a piece gains or loses by standing on a square, by phase, then piece.
+/// Every side looks at its maps from its own end of the board, the last row is where its pieces start.
+type HeatMaps = [[Array2<f64>; 6]; 2];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Model {
     depth: u8,
-    heat_maps: [Array2<f64>; 6],
+    /// What a piece is worth wherever it stands, by piece
+    material: [f64; 6],
+    /// Used by both sides, unless black has its own
+    heat_maps: HeatMaps,
+    black_heat_maps: Option<HeatMaps>,
+    /// How far this model's heat maps move when they mutate self-adaptively, times the spread of the mutation
+    mutation_scale: f64,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Model {
+    /// The usual piece values and empty heat maps, so only material counts
     fn new() -> Self {
         Self {
             depth: 4,
-            heat_maps: [
-                Array2::from_elem([8, 8], 1.0),
-                Array2::from_elem([8, 8], 1.0),
-                Array2::from_elem([8, 8], 1.0),
-                Array2::from_elem([8, 8], 1.0),
-                Array2::from_elem([8, 8], 1.0),
-                Array2::from_elem([8, 8], 1.0),
-            ],
+            material: ChessPiece::ALL.map(|piece| piece.value() as f64),
+            heat_maps: std::array::from_fn(|_| std::array::from_fn(|_| Array2::zeros([8, 8]))),
+            black_heat_maps: None,
+            mutation_scale: 1.0,
+        }
+    }
+
+    /// Gives black a copy of the heat maps to go its own way from, instead of sharing white's.
+    /// Shared maps play both sides the same, separate ones can learn different plans for each.
+    pub fn separate_colors(&mut self) {
+        if self.black_heat_maps.is_none() {
+            self.black_heat_maps = Some(self.heat_maps.clone());
+        }
+    }
+
+    pub fn has_separate_colors(&self) -> bool {
+        self.black_heat_maps.is_some()
+    }
+
+    fn heat_maps_of(&self, color: Color) -> &HeatMaps {
+        match (color, &self.black_heat_maps) {
+            (Color::Black, Some(heat_maps)) => heat_maps,
+            _ => &self.heat_maps,
+        }
+    }
+
+    fn all_heat_maps_mut(&mut self) -> impl Iterator<Item = &mut Array2<f64>> {
+        self.heat_maps
+            .iter_mut()
+            .chain(self.black_heat_maps.iter_mut().flatten())
+            .flatten()
+    }
+
+    pub fn randomize_heat_maps(&mut self, mean: f64, std_dev: f64, rng: &mut impl Rng) {
+        let normal = Normal::new(mean, std_dev).unwrap();
+        for heat_map in self.all_heat_maps_mut() {
+            *heat_map = Array2::from_shape_fn([8, 8], |_| normal.sample(rng));
         }
     }
 
-    pub fn randomize_heat_maps(&mut self, mean: f64, std_dev: f64) {
-        for heat_map in self.heat_maps.iter_mut() {
-            let mut rng = rand::thread_rng();
-            let normal = Normal::new(mean, std_dev).unwrap();
+    pub fn mutation_scale(&self) -> f64 {
+        self.mutation_scale
+    }
 
-            *heat_map = Array2::from_shape_fn([8, 8], |_| normal.sample(&mut rng));
+    /// Changes the tiles as `mutation` says. Mutating self-adaptively changes the model's own scale first.
+    /// Panics for settings `HeatMapMutation::validate` rejects.
+    pub fn mutate_heat_maps(&mut self, mutation: &HeatMapMutation, rng: &mut impl Rng) {
+        let scale = match &mutation.self_adaptive {
+            Some(adaptation) => {
+                self.mutation_scale =
+                    adaptation.adapt(self.mutation_scale, StandardNormal.sample(rng));
+                self.mutation_scale
+            }
+            None => 1.0,
+        };
+        let black_heat_maps = self.black_heat_maps.iter_mut();
+        for heat_maps in std::iter::once(&mut self.heat_maps).chain(black_heat_maps) {
+            for phase_maps in heat_maps {
+                for (piece, heat_map) in ChessPiece::ALL.into_iter().zip(phase_maps) {
+                    mutation.mutate(heat_map, mutation.std_dev_for(piece) * scale, rng);
+                }
+            }
         }
     }
 
-    pub fn mutate_heat_maps(&mut self, std_dev: f64) {
-        let mut rng = rand::thread_rng();
-        for heat_map in self.heat_maps.iter_mut() {
-            for tile in heat_map {
-                let normal = Normal::new(*tile, std_dev).unwrap();
-                *tile = normal.sample(&mut rng);
+    /// Shifts every piece value by a normal distributed amount, the king's stays 0
+    pub fn mutate_material(&mut self, std_dev: f64, rng: &mut impl Rng) {
+        for piece in ChessPiece::ALL {
+            if piece != ChessPiece::King {
+                let value = &mut self.material[piece as usize];
+                *value = Normal::new(*value, std_dev).unwrap().sample(rng);
             }
         }
     }
 
-    pub fn breed_heat_maps(&self, other: &Self) -> Self {
+    /// A child taking every tile and piece value from one of the two parents at random.
+    /// It has separate maps for black if `self` has, its mutation scale is in between the parents'.
+    pub fn breed_heat_maps(&self, other: &Self, rng: &mut impl Rng) -> Self {
         let mut child = Model::new();
-        let mut rng = rand::thread_rng();
-        for heat_map_idx in 0..6 {
-            Zip::from(&mut child.heat_maps[heat_map_idx])
-                .and(&self.heat_maps[heat_map_idx])
-                .and(&other.heat_maps[heat_map_idx])
-                .for_each(|child, &own, &other| {
-                    if rng.gen::<bool>() {
-                        *child = own;
-                    } else {
-                        *child = other;
-                    }
-                })
+        if self.has_separate_colors() {
+            child.separate_colors();
+        }
+        for color in [Color::White, Color::Black] {
+            let child_maps = match (color, &mut child.black_heat_maps) {
+                (Color::White, _) => &mut child.heat_maps,
+                (Color::Black, Some(heat_maps)) => heat_maps,
+                (Color::Black, None) => break,
+            };
+            let (own_maps, other_maps) = (self.heat_maps_of(color), other.heat_maps_of(color));
+            for phase in 0..2 {
+                for piece in 0..6 {
+                    Zip::from(&mut child_maps[phase][piece])
+                        .and(&own_maps[phase][piece])
+                        .and(&other_maps[phase][piece])
+                        .for_each(|child, &own, &other| {
+                            if rng.gen::<bool>() {
+                                *child = own;
+                            } else {
+                                *child = other;
+                            }
+                        })
+                }
+            }
         }
+        for (child, (&own, &other)) in child
+            .material
+            .iter_mut()
+            .zip(self.material.iter().zip(&other.material))
+        {
+            *child = if rng.gen::<bool>() { own } else { other };
+        }
+        child.mutation_scale = (self.mutation_scale * other.mutation_scale).sqrt();
 
         child
     }
 
-    pub fn get_mut_heat_map_for(&mut self, piece: ChessPiece) -> &mut Array2<f64> {
-        let heat_map_idx = match piece {
-            ChessPiece::Pawn => 0,
-            ChessPiece::Bishoph => 1,
-            ChessPiece::Knight => 2,
-            ChessPiece::Rook => 3,
-            ChessPiece::Queen => 4,
-            ChessPiece::King => 5,
+    /// The map `color` uses, seen from its own end of the board. Without separate colors both get the same one.
+    pub fn get_mut_heat_map_for(
+        &mut self,
+        piece: ChessPiece,
+        color: Color,
+        phase: GamePhase,
+    ) -> &mut Array2<f64> {
+        let heat_maps = match (color, &mut self.black_heat_maps) {
+            (Color::Black, Some(heat_maps)) => heat_maps,
+            _ => &mut self.heat_maps,
         };
-        &mut self.heat_maps[heat_map_idx]
-    }
-    pub fn get_heat_map_for(&self, piece: ChessPiece) -> &Array2<f64> {
-        let heat_map_idx = match piece {
-            ChessPiece::Pawn => 0,
-            ChessPiece::Bishoph => 1,
-            ChessPiece::Knight => 2,
-            ChessPiece::Rook => 3,
-            ChessPiece::Queen => 4,
-            ChessPiece::King => 5,
-        };
-        &self.heat_maps[heat_map_idx]
+        &mut heat_maps[phase as usize][piece as usize]
+    }
+    pub fn get_heat_map_for(
+        &self,
+        piece: ChessPiece,
+        color: Color,
+        phase: GamePhase,
+    ) -> &Array2<f64> {
+        &self.heat_maps_of(color)[phase as usize][piece as usize]
     }
 
-    /// Grades a board for white, according to the heat map
-    /// use negative score for black
-    pub fn grade_board(&self, board: &ChessBoard) -> f64 {
-        let mut score = 0.0;
-        for (piece, color, position) in board.get_all_pieces_and_positions() {
-            let mut delta = self.get_heat_map_for(piece)[position.get_idx()];
-            if color == Color::Black {
-                delta *= -1.0;
-            }
-            score += delta;
-        }
+    pub fn get_mut_material_for(&mut self, piece: ChessPiece) -> &mut f64 {
+        &mut self.material[piece as usize]
+    }
+    pub fn get_material_for(&self, piece: ChessPiece) -> f64 {
+        self.material[piece as usize]
+    }
 
-        score
+    /// How far the game is from the endgame, from 1 with all pieces on the board down to 0 with only pawns and kings.
+    /// Promotions can push the count above the start, that still counts as 1.
+    pub fn game_phase(board: &ChessBoard) -> f64 {
+        phase_of(board.fields.iter().flatten().map(|(piece, _)| *piece))
     }
 
-    pub fn grade_moves(
+    /// What a `piece` of `color` standing on `position` is worth to its side, `phase` as from `game_phase`
+    fn piece_score(
         &self,
-        board: ChessBoard,
-        own_color: Color,
-        depth: u8,
-    ) -> Vec<(BoardPosition, BoardPosition, f64)> {
-        // let mut scored_moves = Vec::new();
-        let data = board.get_all_pieces_and_positions();
-        data.par_iter()
-            .fold(
-                || Vec::new(),
-                |mut scored_moves, (_piece, piece_color, from)| {
-                    if own_color == *piece_color {
-                        for to in ChessPiece::get_moves(&from, &board)
-                            .indexed_iter()
-                            .filter_map(|((row, col), can_move)| {
-                                can_move.then_some(BoardPosition::from_idx(row, col))
-                            })
-                        {
-                            let mut moved_board = board.clone();
-                            if moved_board.move_piece(&from, &to) {
-                                let score = if depth < self.depth {
-                                    -self
-                                        .grade_moves(moved_board, !own_color, depth + 1)
-                                        .iter()
-                                        .map(|(_, _, score)| score)
-                                        .sum::<f64>()
-                                } else {
-                                    self.grade_board(&moved_board)
-                                        * match own_color {
-                                            Color::Black => -1.0,
-                                            Color::White => 1.0,
-                                        }
-                                };
-                                scored_moves.push((from.clone(), to, score))
-                            }
-                        }
+        piece: ChessPiece,
+        color: Color,
+        position: &BoardPosition,
+        phase: f64,
+    ) -> f64 {
+        let [middlegame, endgame] = self.heat_maps_of(color);
+        let idx = own_view(position, color).get_idx();
+        self.material[piece as usize]
+            + phase * middlegame[piece as usize][idx]
+            + (1.0 - phase) * endgame[piece as usize][idx]
+    }
+
+    /// Grades a board for white: material plus heat maps, blending the middlegame and endgame maps by `game_phase`.
+    /// use negative score for black
+    pub fn grade_board(&self, board: &ChessBoard) -> f64 {
+        let phase = Self::game_phase(board);
+        // both sides add up their pieces from their own end of the board, so a mirrored
+        // position comes out as exactly 0 and not as a rounding error
+        let side_score = |color| {
+            let mut score = 0.0;
+            for idx in 0..64 {
+                let position = own_view(&BoardPosition::from_idx(idx / 8, idx % 8), color);
+                if let Some((piece, piece_color)) = board.fields[position.get_idx()] {
+                    if piece_color == color {
+                        score += self.piece_score(piece, color, &position, phase);
                     }
-                    scored_moves
-                },
-            )
-            .reduce(|| Vec::new(), |a, b| [a, b].concat())
-
-        // scored_moves
-        // for (_piece, piece_color, from) in data.par_iter() {
-        //     if own_color != piece_color {
-        //         continue;
-        //     }
-
-        //     for to in ChessPiece::get_moves(&from, &board)
-        //         .indexed_iter()
-        //         .filter_map(|((row, col), can_move)| {
-        //             can_move.then_some(BoardPosition::from_idx(row, col))
-        //         })
-        //     {
-        //         let mut moved_board = board.clone();
-        //         if moved_board.move_piece(&from, &to) {
-        //             let score = if depth < self.depth {
-        //                 -self
-        //                     .grade_moves(moved_board, !own_color, depth + 1)
-        //                     .iter()
-        //                     .map(|(_, _, score)| score)
-        //                     .sum::<f64>()
-        //             } else {
-        //                 self.grade_board(&moved_board)
-        //                     * match own_color {
-        //                         Color::Black => -1.0,
-        //                         Color::White => 1.0,
-        //                     }
-        //             };
-        //             scored_moves.push((from, to, score))
-        //         }
-        //     }
-        // }
-
-        // scored_moves
+                }
+            }
+            score
+        };
+
+        side_score(Color::White) - side_score(Color::Black)
     }
-}
 
-// #[derive(Clone, Debug)]
-// pub struct AppliedModel {
-//     pub color: Color,
-//     pub model: Model,
-// }
+    /// How much `chess_move` changes `grade_board` in favour of the side playing it.
+    /// A capture shifts the phase a little, that is left out.
+    fn move_gain(&self, board: &ChessBoard, chess_move: &Move) -> f64 {
+        let Some((piece, color)) = board.get_piece_at_position(&chess_move.from) else {
+            return 0.0;
+        };
+        let phase = Self::game_phase(board);
+        let arriving = board.promotion_piece(chess_move).unwrap_or(piece);
+        let mut gain = self.piece_score(arriving, color, &chess_move.to, phase)
+            - self.piece_score(piece, color, &chess_move.from, phase);
+        if let Some((captured, position)) = board.captured_piece(chess_move) {
+            gain += self.piece_score(captured, !color, &position, phase);
+        }
+        gain
+    }
 
-// impl AppliedModel {
-//     pub fn new(color: Color, model: Model) -> Self {
-//         Self { color, model }
-//     }
+    pub fn grade_moves(&self, board: ChessBoard, depth: u8) -> Vec<(Move, f64)> {
+        let stop = AtomicBool::new(false);
+        Search::new(
+            self,
+            &TranspositionTable::new(ONE_OFF_TABLE_MB),
+            &stop,
+            None,
+            None,
+        )
+        .grade_moves(&board, self.depth.saturating_sub(depth) + 1)
+        .unwrap_or_default()
+    }
+}
 
-//     // fn play_against(&self, other: AppliedModel) -> Option<f64> {
-//     //     if other.color == self.color {
-//     //         return None;
-//     //     }
+/// Where `position` is for `color` looking from its own end of the board, which is the same for white.
+/// Doing it twice gives back `position`.
+fn own_view(position: &BoardPosition, color: Color) -> BoardPosition {
+    match color {
+        Color::White => *position,
+        Color::Black => BoardPosition {
+            x: position.x,
+            y: 7 - position.y,
+        },
+    }
+}
 
-//     // }
-// }
+/// See `Model::game_phase`
+fn phase_of(pieces: impl Iterator<Item = ChessPiece>) -> f64 {
+    let phase: u32 = pieces
+        .map(|piece| match piece {
+            ChessPiece::Knight | ChessPiece::Bishoph => 1,
+            ChessPiece::Rook => 2,
+            ChessPiece::Queen => 4,
+            ChessPiece::Pawn | ChessPiece::King => 0,
+        })
+        .sum();
+    phase.min(OPENING_PHASE) as f64 / OPENING_PHASE as f64
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::BoardPosition;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     #[test]
     fn basic_scoring() {
         let mut model = Model::new();
-        model.get_mut_heat_map_for(ChessPiece::Pawn)[BoardPosition { x: 0, y: 5 }.get_idx()] = 20.0;
+        model.get_mut_heat_map_for(ChessPiece::Pawn, Color::White, GamePhase::Middlegame)
+            [BoardPosition { x: 0, y: 5 }.get_idx()] = 20.0;
         let mut board = ChessBoard::init_default();
         let score = model.grade_board(&board);
         assert_eq!(score, 0.0);
         assert!(board.move_piece(&BoardPosition { x: 0, y: 6 }, &BoardPosition { x: 0, y: 5 }));
         let score = model.grade_board(&board);
-        assert_eq!(score, 19.0);
+        assert_eq!(score, 20.0);
+    }
+
+    #[test]
+    fn counts_material() {
+        let model = Model::new();
+        // white is a rook up, black has an extra pawn
+        let board = ChessBoard::from_fen("4k3/pp6/8/8/8/8/P7/R3K3 w - - 0 1").unwrap();
+        assert_eq!(model.grade_board(&board), 5.0 + 1.0 - 2.0);
+    }
+
+    #[test]
+    fn tapers_by_phase() {
+        let mut model = Model::new();
+        let e1 = BoardPosition { x: 4, y: 7 }.get_idx();
+        model.get_mut_heat_map_for(ChessPiece::King, Color::White, GamePhase::Middlegame)[e1] = 1.0;
+        model.get_mut_heat_map_for(ChessPiece::King, Color::White, GamePhase::Endgame)[e1] = 3.0;
+
+        // the black king has left e8, its own e1
+        let opening =
+            ChessBoard::from_fen("rnbq1bnr/pppp1ppp/4k3/4p3/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1")
+                .unwrap();
+        assert_eq!(Model::game_phase(&opening), 1.0);
+        assert_eq!(model.grade_board(&opening), 1.0);
+
+        let endgame = ChessBoard::from_fen("3k4/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+        assert_eq!(Model::game_phase(&endgame), 0.0);
+        assert_eq!(model.grade_board(&endgame), 3.0);
+
+        // a queen and a rook each, half of the opening
+        let halfway = ChessBoard::from_fen("3q1k1r/8/8/8/8/8/8/3QK2R w - - 0 1").unwrap();
+        assert_eq!(Model::game_phase(&halfway), 0.5);
+        assert_eq!(model.grade_board(&halfway), 2.0);
+    }
+
+    #[test]
+    fn start_scores_zero_for_any_model() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let mut model = Model::new();
+            model.randomize_heat_maps(0.0, 10.0, &mut rng);
+            model.mutate_material(3.0, &mut rng);
+            let mut board = ChessBoard::init_default();
+            assert_eq!(model.grade_board(&board), 0.0);
+            // still level after black mirrors white's moves
+            for uci in ["e2e4", "e7e5", "g1f3", "g8f6"] {
+                assert!(board.play_move(&uci.parse().unwrap()));
+            }
+            assert_eq!(model.grade_board(&board), 0.0);
+        }
+    }
+
+    #[test]
+    fn black_looks_from_its_own_side() {
+        let mut model = Model::new();
+        let a3 = BoardPosition { x: 0, y: 5 }.get_idx();
+        model.get_mut_heat_map_for(ChessPiece::Pawn, Color::White, GamePhase::Middlegame)[a3] = 2.0;
+        // a pawn one step ahead is worth as much to both sides
+        let board =
+            ChessBoard::from_fen("rnbqkbnr/1ppppppp/p7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 2")
+                .unwrap();
+        assert_eq!(model.grade_board(&board), -2.0);
+
+        // black can go its own way
+        model.separate_colors();
+        assert!(model.has_separate_colors());
+        model.get_mut_heat_map_for(ChessPiece::Pawn, Color::Black, GamePhase::Middlegame)[a3] = 5.0;
+        assert_eq!(model.grade_board(&board), -5.0);
+        let child = model.breed_heat_maps(&Model::new(), &mut StdRng::seed_from_u64(0));
+        assert!(child.has_separate_colors());
+    }
+
+    #[test]
+    fn mutates_per_piece() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mutation = HeatMapMutation::gaussian(0.0);
+        mutation.set_std_dev_for(ChessPiece::Knight, 1.0);
+        let mut model = Model::new();
+        model.mutate_heat_maps(&mutation, &mut rng);
+        for piece in ChessPiece::ALL {
+            let heat_map = model.get_heat_map_for(piece, Color::White, GamePhase::Endgame);
+            assert_eq!(
+                heat_map.iter().all(|&tile| tile == 0.0),
+                piece != ChessPiece::Knight
+            );
+        }
+        assert_eq!(model.mutation_scale(), 1.0);
+
+        // self-adaptive models step further or shorter, and children are in between
+        mutation.self_adaptive = Some(SelfAdaptation::new(0.5));
+        let mut other = Model::new();
+        model.mutate_heat_maps(&mutation, &mut rng);
+        other.mutate_heat_maps(&mutation, &mut rng);
+        assert_ne!(model.mutation_scale(), 1.0);
+        assert_ne!(model.mutation_scale(), other.mutation_scale());
+        let child = model.breed_heat_maps(&other, &mut rng);
+        assert!(
+            (child.mutation_scale() - (model.mutation_scale() * other.mutation_scale()).sqrt())
+                .abs()
+                < 1e-12
+        );
     }
 
     #[test]
@@ -225,12 +442,75 @@ mod test {
         // 2: 0.02 s
         // 1: 0.00 s
 
-        model.get_mut_heat_map_for(ChessPiece::Pawn)[BoardPosition { x: 0, y: 5 }.get_idx()] = 20.0;
-        model.get_mut_heat_map_for(ChessPiece::Pawn)[BoardPosition { x: 0, y: 2 }.get_idx()] = 20.0;
+        // a3 for white, a6 for black
+        model.get_mut_heat_map_for(ChessPiece::Pawn, Color::White, GamePhase::Middlegame)
+            [BoardPosition { x: 0, y: 5 }.get_idx()] = 20.0;
 
         let board = ChessBoard::init_default();
 
-        let grades = model.grade_moves(board, Color::White, 0);
-        dbg!(grades);
+        // two half moves deep: after a3 black answers a6 and it is even, after anything else black is ahead
+        let grades = model.grade_moves(board, 0);
+        assert_eq!(grades.len(), 20);
+        let a3 = "a2a3".parse().unwrap();
+        let (_, best) = grades
+            .iter()
+            .find(|(chess_move, _)| *chess_move == a3)
+            .unwrap();
+        assert!(grades
+            .iter()
+            .all(|(chess_move, score)| *chess_move == a3 || score < best));
+    }
+
+    #[test]
+    fn grades_all_promotions() {
+        let mut model = Model::new();
+        model.depth = 0;
+        let mut board = ChessBoard::new();
+        board.fields[BoardPosition { x: 0, y: 1 }.get_idx()] =
+            Some((ChessPiece::Pawn, Color::White));
+
+        let grades = model.grade_moves(board, 0);
+        let mut promotions: Vec<_> = grades
+            .iter()
+            .filter_map(|(chess_move, _)| chess_move.promotion)
+            .collect();
+        promotions.sort_by_key(|piece| *piece as u8);
+        assert_eq!(grades.len(), 4);
+        assert_eq!(
+            promotions,
+            vec![
+                ChessPiece::Bishoph,
+                ChessPiece::Rook,
+                ChessPiece::Knight,
+                ChessPiece::Queen
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_mate() {
+        let mut model = Model::new();
+        model.depth = 1;
+        let mut board = ChessBoard::init_default();
+        for (from, to) in [((5, 6), (5, 5)), ((4, 1), (4, 3)), ((6, 6), (6, 4))] {
+            assert!(board.move_piece(
+                &BoardPosition {
+                    x: from.0,
+                    y: from.1
+                },
+                &BoardPosition { x: to.0, y: to.1 }
+            ));
+        }
+
+        let (best_move, score) = model
+            .grade_moves(board, 0)
+            .into_iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+        assert_eq!(
+            best_move,
+            Move::new(BoardPosition { x: 3, y: 0 }, BoardPosition { x: 7, y: 4 })
+        );
+        assert_eq!(score, MATE_SCORE);
     }
 }
@@ -0,0 +1,305 @@
+use crate::ChessPiece;
+use ndarray::{Array2, Zip};
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How `Model::mutate_heat_maps` changes the tiles
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeatMapMutation {
+    /// Chance of every tile to change at all, 1 changes all of them
+    pub probability: f64,
+    /// How far a tile moves, by piece
+    pub std_devs: [f64; 6],
+    /// Every model carries its own scale for `std_devs`, which mutates before the tiles do.
+    /// Scales that make better children are passed on, so the run finds out for itself how far to step.
+    pub self_adaptive: Option<SelfAdaptation>,
+    /// From 0 to 1, how much of a tile's change is the average change of the tiles around it.
+    /// The higher, the more neighbouring squares move together.
+    pub smoothing: f64,
+    /// The lowest and highest value a tile can end up with
+    pub clamp: Option<(f64, f64)>,
+}
+
+impl HeatMapMutation {
+    /// Every tile of every map moves by a normal distributed amount, the same spread for all pieces
+    pub fn gaussian(std_dev: f64) -> Self {
+        Self {
+            probability: 1.0,
+            std_devs: [std_dev; 6],
+            self_adaptive: None,
+            smoothing: 0.0,
+            clamp: None,
+        }
+    }
+
+    /// Fails for settings `mutate_heat_maps` can't work with, they may come from a hand edited checkpoint
+    pub fn validate(&self) -> Result<(), MutationError> {
+        if !(0.0..=1.0).contains(&self.probability) {
+            return Err(MutationError::Probability(self.probability));
+        }
+        if let Some(&std_dev) = self
+            .std_devs
+            .iter()
+            .find(|std_dev| !(std_dev.is_finite() && **std_dev >= 0.0))
+        {
+            return Err(MutationError::StdDev(std_dev));
+        }
+        if !(0.0..=1.0).contains(&self.smoothing) {
+            return Err(MutationError::Smoothing(self.smoothing));
+        }
+        if let Some((min, max)) = self.clamp {
+            if min.is_nan() || max.is_nan() || min > max {
+                return Err(MutationError::Clamp(min, max));
+            }
+        }
+        if let Some(adaptation) = &self.self_adaptive {
+            adaptation.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn std_dev_for(&self, piece: ChessPiece) -> f64 {
+        self.std_devs[piece as usize]
+    }
+
+    pub fn set_std_dev_for(&mut self, piece: ChessPiece, std_dev: f64) {
+        self.std_devs[piece as usize] = std_dev;
+    }
+
+    /// Moves the tiles of one map, `std_dev` is the spread before smoothing
+    pub(super) fn mutate(&self, heat_map: &mut Array2<f64>, std_dev: f64, rng: &mut impl Rng) {
+        let normal = Normal::new(0.0, std_dev).unwrap();
+        let noise = Array2::from_shape_fn([8, 8], |_| normal.sample(rng));
+        Zip::from(heat_map)
+            .and(&smooth(&noise, self.smoothing))
+            .for_each(|tile, &change| {
+                if rng.gen_bool(self.probability) {
+                    *tile += change;
+                }
+                if let Some((min, max)) = self.clamp {
+                    *tile = tile.clamp(min, max);
+                }
+            });
+    }
+}
+
+/// How a model's mutation scale changes, see `HeatMapMutation::self_adaptive`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SelfAdaptation {
+    /// Spread of the logarithm of the scale's change
+    pub learning_rate: f64,
+    /// The scale stays between these, so it can neither die out nor blow up
+    pub min_scale: f64,
+    pub max_scale: f64,
+}
+
+impl SelfAdaptation {
+    /// A scale from a tenth to ten times the configured spread
+    pub fn new(learning_rate: f64) -> Self {
+        Self {
+            learning_rate,
+            min_scale: 0.1,
+            max_scale: 10.0,
+        }
+    }
+
+    fn validate(&self) -> Result<(), MutationError> {
+        if !(self.learning_rate.is_finite() && self.learning_rate >= 0.0) {
+            return Err(MutationError::LearningRate(self.learning_rate));
+        }
+        if !(self.min_scale > 0.0 && self.min_scale <= self.max_scale && self.max_scale.is_finite())
+        {
+            return Err(MutationError::ScaleRange(self.min_scale, self.max_scale));
+        }
+        Ok(())
+    }
+
+    /// The scale after one step of `step`, a standard normal number
+    pub(super) fn adapt(&self, scale: f64, step: f64) -> f64 {
+        (scale * (self.learning_rate * step).exp()).clamp(self.min_scale, self.max_scale)
+    }
+}
+
+/// A setting of `HeatMapMutation` out of range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MutationError {
+    /// Not between 0 and 1
+    Probability(f64),
+    /// Negative or not finite
+    StdDev(f64),
+    /// Not between 0 and 1
+    Smoothing(f64),
+    /// The lowest value above the highest
+    Clamp(f64, f64),
+    /// Negative or not finite
+    LearningRate(f64),
+    /// Not above 0 or the lowest above the highest
+    ScaleRange(f64, f64),
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Probability(probability) => {
+                write!(
+                    f,
+                    "mutation probability {probability} isn't between 0 and 1"
+                )
+            }
+            Self::StdDev(std_dev) => write!(f, "mutation std dev {std_dev} isn't 0 or more"),
+            Self::Smoothing(smoothing) => {
+                write!(f, "smoothing {smoothing} isn't between 0 and 1")
+            }
+            Self::Clamp(min, max) => write!(f, "clamp range {min} to {max} is empty"),
+            Self::LearningRate(rate) => write!(f, "learning rate {rate} isn't 0 or more"),
+            Self::ScaleRange(min, max) => {
+                write!(
+                    f,
+                    "mutation scale range {min} to {max} isn't above 0 and in order"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MutationError {}
+
+/// Blends every tile's change with the average change of the tiles around it
+fn smooth(noise: &Array2<f64>, smoothing: f64) -> Array2<f64> {
+    if smoothing == 0.0 {
+        return noise.clone();
+    }
+    Array2::from_shape_fn([8, 8], |(y, x)| {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for neighbour_y in y.saturating_sub(1)..=(y + 1).min(7) {
+            for neighbour_x in x.saturating_sub(1)..=(x + 1).min(7) {
+                if (neighbour_y, neighbour_x) != (y, x) {
+                    sum += noise[[neighbour_y, neighbour_x]];
+                    count += 1;
+                }
+            }
+        }
+        (1.0 - smoothing) * noise[[y, x]] + smoothing * sum / count as f64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn probability_and_clamp() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut heat_map = Array2::zeros([8, 8]);
+        let mutation = HeatMapMutation {
+            probability: 0.0,
+            ..HeatMapMutation::gaussian(1.0)
+        };
+        mutation.mutate(&mut heat_map, 1.0, &mut rng);
+        assert!(heat_map.iter().all(|&tile| tile == 0.0));
+
+        let mutation = HeatMapMutation {
+            probability: 0.5,
+            clamp: Some((-0.1, 0.2)),
+            ..HeatMapMutation::gaussian(1.0)
+        };
+        mutation.mutate(&mut heat_map, 1.0, &mut rng);
+        let unchanged = heat_map.iter().filter(|&&tile| tile == 0.0).count();
+        assert!((10..54).contains(&unchanged), "{unchanged}");
+        assert!(heat_map.iter().all(|&tile| (-0.1..=0.2).contains(&tile)));
+        assert!(heat_map.iter().any(|&tile| tile == 0.2));
+    }
+
+    #[test]
+    fn smoothing_moves_neighbours_together() {
+        // how far apart the changes of neighbouring tiles are, on average
+        let roughness = |smoothing| {
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut heat_map = Array2::zeros([8, 8]);
+            HeatMapMutation {
+                smoothing,
+                ..HeatMapMutation::gaussian(1.0)
+            }
+            .mutate(&mut heat_map, 1.0, &mut rng);
+            let steps = heat_map
+                .windows([1, 2])
+                .into_iter()
+                .map(|pair| (pair[[0, 0]] - pair[[0, 1]]).abs());
+            steps.sum::<f64>() / 56.0
+        };
+        assert!(roughness(0.8) < roughness(0.0) / 2.0);
+
+        let noise = Array2::from_shape_fn([8, 8], |(y, x)| (y * 8 + x) as f64);
+        assert_eq!(smooth(&noise, 0.0), noise);
+        // a corner has three neighbours
+        assert_eq!(smooth(&noise, 1.0)[[0, 0]], (1.0 + 8.0 + 9.0) / 3.0);
+    }
+
+    #[test]
+    fn validates_the_settings() {
+        let gaussian = HeatMapMutation::gaussian(0.1);
+        assert_eq!(gaussian.validate(), Ok(()));
+        let invalid = [
+            (
+                HeatMapMutation {
+                    probability: 1.5,
+                    ..gaussian.clone()
+                },
+                MutationError::Probability(1.5),
+            ),
+            (HeatMapMutation::gaussian(-1.0), MutationError::StdDev(-1.0)),
+            (
+                HeatMapMutation {
+                    smoothing: 2.0,
+                    ..gaussian.clone()
+                },
+                MutationError::Smoothing(2.0),
+            ),
+            (
+                HeatMapMutation {
+                    clamp: Some((1.0, -1.0)),
+                    ..gaussian.clone()
+                },
+                MutationError::Clamp(1.0, -1.0),
+            ),
+            (
+                HeatMapMutation {
+                    self_adaptive: Some(SelfAdaptation {
+                        min_scale: 0.0,
+                        ..SelfAdaptation::new(0.2)
+                    }),
+                    ..gaussian.clone()
+                },
+                MutationError::ScaleRange(0.0, 10.0),
+            ),
+        ];
+        for (mutation, error) in invalid {
+            assert_eq!(mutation.validate(), Err(error));
+        }
+        // NaN isn't in any range
+        let nan = HeatMapMutation {
+            probability: f64::NAN,
+            ..gaussian.clone()
+        };
+        assert!(matches!(nan.validate(), Err(MutationError::Probability(_))));
+        let mut nan = gaussian;
+        nan.std_devs[2] = f64::NAN;
+        assert!(matches!(nan.validate(), Err(MutationError::StdDev(_))));
+        nan.std_devs[2] = 0.1;
+        nan.clamp = Some((f64::NAN, 1.0));
+        assert!(matches!(nan.validate(), Err(MutationError::Clamp(..))));
+    }
+
+    #[test]
+    fn scale_stays_in_range() {
+        let adaptation = SelfAdaptation::new(1.0);
+        assert_eq!(adaptation.adapt(1.0, 100.0), 10.0);
+        assert_eq!(adaptation.adapt(1.0, -100.0), 0.1);
+        assert_eq!(adaptation.adapt(2.0, 0.0), 2.0);
+    }
+}
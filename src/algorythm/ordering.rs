@@ -0,0 +1,138 @@
+use super::transposition::{pack_move, unpack_move};
+use crate::{ChessBoard, Move};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Marks a used killer slot, an empty one is 0
+const KILLER_SET: u64 = 1 << 15;
+
+// Ranks of the kinds of moves, the history of a quiet move stays far below all of them
+const HASH_MOVE: u64 = u64::MAX;
+const CAPTURE: u64 = 1 << 62;
+const KILLER: u64 = 1 << 61;
+
+/// Decides which moves the search tries first. The sooner it finds a good move, the more of the others
+/// it can cut off. Like the transposition table, the threads of a search share it without locks.
+pub(super) struct MoveOrdering {
+    /// The last two quiet moves that caused a cutoff, by ply. Moves good in one line tend to be good in its neighbours too.
+    killers: Box<[[AtomicU64; 2]]>,
+    /// How much cutting off quiet moves have done, by from and to square
+    history: Box<[AtomicU64]>,
+}
+
+impl MoveOrdering {
+    pub(super) fn new() -> Self {
+        Self {
+            killers: (0..=u8::MAX).map(|_| Default::default()).collect(),
+            history: (0..64 * 64).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Sorts `moves` best first: the move the transposition table remembers, captures and promotions by
+    /// most valuable victim and least valuable attacker, the killers of `ply`, then the quiet moves by history
+    pub(super) fn order(
+        &self,
+        board: &ChessBoard,
+        moves: &mut [Move],
+        hash_move: Option<Move>,
+        ply: u8,
+    ) {
+        let killers = self.killers(ply);
+        moves.sort_by_cached_key(|chess_move| {
+            Reverse(if hash_move == Some(*chess_move) {
+                HASH_MOVE
+            } else if let Some(rank) = capture_rank(board, chess_move) {
+                CAPTURE + rank
+            } else if let Some(index) = killers
+                .iter()
+                .position(|killer| killer == &Some(*chess_move))
+            {
+                KILLER + 1 - index as u64
+            } else {
+                self.history[history_index(chess_move)].load(Ordering::Relaxed)
+            })
+        });
+    }
+
+    /// Remembers that `chess_move` cut off the search `depth` half moves from the leaves,
+    /// if it is quiet. Captures are tried early anyway.
+    pub(super) fn cutoff(&self, board: &ChessBoard, chess_move: &Move, depth: u8, ply: u8) {
+        if capture_rank(board, chess_move).is_some() {
+            return;
+        }
+        // cutoffs far from the leaves save the most
+        self.history[history_index(chess_move)]
+            .fetch_add(depth as u64 * depth as u64, Ordering::Relaxed);
+
+        let [first, second] = &self.killers[ply as usize];
+        let packed = pack_move(chess_move) | KILLER_SET;
+        let previous = first.swap(packed, Ordering::Relaxed);
+        if previous != packed {
+            second.store(previous, Ordering::Relaxed);
+        }
+    }
+
+    fn killers(&self, ply: u8) -> [Option<Move>; 2] {
+        self.killers[ply as usize].each_ref().map(|killer| {
+            let packed = killer.load(Ordering::Relaxed);
+            (packed & KILLER_SET != 0).then(|| unpack_move(packed))
+        })
+    }
+}
+
+/// `None` for quiet moves, otherwise higher for bigger victims and promotions, then for cheaper attackers
+fn capture_rank(board: &ChessBoard, chess_move: &Move) -> Option<u64> {
+    let captured = board.captured_piece(chess_move);
+    let promotion = board.promotion_piece(chess_move);
+    if captured.is_none() && promotion.is_none() {
+        return None;
+    }
+    let gained =
+        captured.map_or(0, |(piece, _)| piece.value()) + promotion.map_or(0, |piece| piece.value());
+    let attacker = board
+        .get_piece_at_position(&chess_move.from)
+        .map_or(0, |(piece, _)| piece.value());
+    Some(gained as u64 * 16 + (15 - attacker as u64))
+}
+
+fn history_index(chess_move: &Move) -> usize {
+    // the from and to squares are the lowest 12 bits
+    (pack_move(chess_move) & 0xFFF) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_by_kind() {
+        // the pawn can take the queen or the rook, the knight only the queen
+        let board = ChessBoard::from_fen("4k3/8/8/2q1r3/3P4/1N6/8/7K w - - 0 1").unwrap();
+        let ordering = MoveOrdering::new();
+        ordering.cutoff(&board, &"h1g1".parse().unwrap(), 3, 1);
+        ordering.cutoff(&board, &"b3a5".parse().unwrap(), 2, 5);
+
+        let mut moves = board.get_legal_moves();
+        ordering.order(&board, &mut moves, Some("b3d2".parse().unwrap()), 1);
+        let first: Vec<String> = moves.iter().take(6).map(Move::to_string).collect();
+        assert_eq!(first, ["b3d2", "d4c5", "b3c5", "d4e5", "h1g1", "b3a5"]);
+    }
+
+    #[test]
+    fn keeps_two_killers() {
+        let board = ChessBoard::init_default();
+        let ordering = MoveOrdering::new();
+        for uci in ["g1f3", "b1c3", "b1c3", "e2e4"] {
+            ordering.cutoff(&board, &uci.parse().unwrap(), 1, 4);
+        }
+        assert_eq!(
+            ordering.killers(4),
+            [Some("e2e4".parse().unwrap()), Some("b1c3".parse().unwrap())]
+        );
+        assert_eq!(ordering.killers(3), [None, None]);
+        // captures don't count
+        let board = ChessBoard::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        ordering.cutoff(&board, &"e4d5".parse().unwrap(), 1, 4);
+        assert_eq!(ordering.killers(4)[0], Some("e2e4".parse().unwrap()));
+    }
+}
@@ -0,0 +1,866 @@
+use super::ordering::MoveOrdering;
+use super::transposition::{Bound, TableEntry, TranspositionTable, ONE_OFF_TABLE_MB};
+use super::{Model, MATE_SCORE};
+use crate::{ChessBoard, Color, Move};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Scores this close to `MATE_SCORE` are mates, the rest of the distance counts half moves until it happens
+const MATE_THRESHOLD: f64 = MATE_SCORE - u8::MAX as f64 - 1.0;
+
+/// How many captures in a row the quiescence search follows at most. When every capture wins about as much as
+/// the next one loses, like with the uniform heat maps of a new model, nothing else stops it.
+const QUIESCENCE_PLIES: u8 = 6;
+
+/// Moves left until the next time control, when the clock doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// When a search has to come back with a move. Without any limit it looks as deep as the model's own depth,
+/// with one it keeps going deeper until the limit is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    /// Half moves to look ahead
+    pub depth: Option<u8>,
+    /// Exactly how long to think
+    pub movetime: Option<Duration>,
+    /// The clock of the side to move, the search decides on its own how much of it to use
+    pub clock: Option<Clock>,
+    /// Positions to visit at most
+    pub nodes: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    pub remaining: Duration,
+    /// Added to the clock after every move
+    pub increment: Duration,
+    /// Moves to play before the next time control adds time, `None` if it never does
+    pub moves_to_go: Option<u32>,
+}
+
+impl SearchLimits {
+    /// How long until the search shouldn't start another iteration, and how long until it has to stop.
+    /// Whatever a GUI sends, absurdly long times only saturate.
+    fn time_budget(&self) -> Option<(Duration, Duration)> {
+        let from_clock = self.clock.map(|clock| {
+            let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let share = (clock.remaining / moves_to_go)
+                .saturating_add(clock.increment.saturating_mul(3) / 4);
+            // never bet more than half of what is left on a single move
+            let hard = share.saturating_mul(3).min(clock.remaining / 2);
+            (share.min(hard), hard)
+        });
+        match (self.movetime.map(|time| (time, time)), from_clock) {
+            (Some((time, _)), Some((soft, hard))) => Some((soft.min(time), hard.min(time))),
+            (movetime, from_clock) => movetime.or(from_clock),
+        }
+    }
+}
+
+/// What a search found, in the shape the engine protocols report it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchInfo {
+    /// Half moves searched
+    pub depth: u8,
+    /// Score of the first move of `pv`, for the side to move
+    pub score: f64,
+    pub nodes: u64,
+    pub time: Duration,
+    /// The moves the search expects to be played, starting with the best one
+    pub pv: Vec<Move>,
+    pub stats: SearchStats,
+}
+
+/// Where the nodes of a search went, to tell how well its pruning works
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Nodes only looking at captures and promotions, they are part of `SearchInfo::nodes` too
+    pub quiescence_nodes: u64,
+    /// Nodes the transposition table answered without a search
+    pub table_hits: u64,
+    /// Nodes that stopped early because a move was too good for the opponent to allow
+    pub cutoffs: u64,
+    /// Cutoffs by the first move tried, the better the move ordering the closer to all of them
+    pub first_move_cutoffs: u64,
+}
+
+impl SearchInfo {
+    /// Full moves until the side to move mates, negative if it gets mated. `None` if the score isn't a mate.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE_THRESHOLD {
+            return None;
+        }
+        // the half move that mates
+        let plies = (MATE_SCORE - self.score.abs()) as i32 + 1;
+        Some(if self.score > 0.0 {
+            (plies + 1) / 2
+        } else {
+            -plies / 2
+        })
+    }
+}
+
+/// State shared by all threads working on one search
+pub(crate) struct Search<'a> {
+    model: &'a Model,
+    table: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    /// Whether the root moves are split between threads. On one thread the same position always gets the same answer.
+    parallel: bool,
+    ordering: MoveOrdering,
+    nodes: AtomicU64,
+    quiescence_nodes: AtomicU64,
+    table_hits: AtomicU64,
+    cutoffs: AtomicU64,
+    first_move_cutoffs: AtomicU64,
+}
+
+impl<'a> Search<'a> {
+    pub(crate) fn new(
+        model: &'a Model,
+        table: &'a TranspositionTable,
+        stop: &'a AtomicBool,
+        deadline: Option<Instant>,
+        node_limit: Option<u64>,
+    ) -> Self {
+        Self {
+            model,
+            table,
+            stop,
+            deadline,
+            node_limit,
+            parallel: true,
+            ordering: MoveOrdering::new(),
+            nodes: AtomicU64::new(0),
+            quiescence_nodes: AtomicU64::new(0),
+            table_hits: AtomicU64::new(0),
+            cutoffs: AtomicU64::new(0),
+            first_move_cutoffs: AtomicU64::new(0),
+        }
+    }
+
+    fn stats(&self) -> SearchStats {
+        SearchStats {
+            quiescence_nodes: self.quiescence_nodes.load(Ordering::Relaxed),
+            table_hits: self.table_hits.load(Ordering::Relaxed),
+            cutoffs: self.cutoffs.load(Ordering::Relaxed),
+            first_move_cutoffs: self.first_move_cutoffs.load(Ordering::Relaxed),
+        }
+    }
+
+    fn aborted(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .node_limit
+                .is_some_and(|limit| self.nodes.load(Ordering::Relaxed) >= limit)
+    }
+
+    /// Scores every legal move exactly, looking `plies` half moves ahead.
+    /// `None` if the search was stopped before it got through all of them.
+    pub(crate) fn grade_moves(&self, board: &ChessBoard, plies: u8) -> Option<Vec<(Move, f64)>> {
+        board
+            .get_legal_moves()
+            .into_par_iter()
+            // every thread walks its own copy of the board
+            .map_init(
+                || board.clone(),
+                |board, chess_move| {
+                    let undo = board.make_move(&chess_move);
+                    let score = self.negamax(
+                        board,
+                        plies.max(1) - 1,
+                        1,
+                        f64::NEG_INFINITY,
+                        f64::INFINITY,
+                        &mut Vec::new(),
+                    );
+                    board.unmake_move(undo);
+                    Some((chess_move, -score?))
+                },
+            )
+            .collect()
+    }
+
+    /// Scores `board` for the side to move, `depth` half moves deep, `ply` half moves below the root.
+    /// Scores at or below `alpha` and at or above `beta` are only bounds, since the search stops
+    /// looking once it knows a score can't matter. `pv` is filled with the best line when the score is exact.
+    fn negamax(
+        &self,
+        board: &mut ChessBoard,
+        depth: u8,
+        ply: u8,
+        mut alpha: f64,
+        beta: f64,
+        pv: &mut Vec<Move>,
+    ) -> Option<f64> {
+        pv.clear();
+        if depth == 0 {
+            return self.quiescence(board, ply, QUIESCENCE_PLIES, alpha, beta);
+        }
+        if self.aborted() {
+            return None;
+        }
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+
+        if board.draw_by_rule().is_some() {
+            return Some(0.0);
+        }
+
+        let hash = board.zobrist_hash();
+        let stored = self.table.probe(hash);
+        if let Some(entry) = stored.filter(|entry| entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if usable {
+                if entry.bound == Bound::Exact {
+                    self.line_from_table(board, depth, pv);
+                }
+                self.table_hits.fetch_add(1, Ordering::Relaxed);
+                return Some(score);
+            }
+        }
+
+        let mut moves = board.generate_legal_moves();
+        if moves.is_empty() {
+            return Some(no_moves_score(board, ply));
+        }
+
+        self.ordering.order(
+            board,
+            &mut moves,
+            stored.and_then(|entry| entry.best_move),
+            ply,
+        );
+
+        let original_alpha = alpha;
+        let mut best = f64::NEG_INFINITY;
+        let mut best_move = moves[0];
+        let mut child_pv = Vec::new();
+        for (index, chess_move) in moves.into_iter().enumerate() {
+            let undo = board.make_move(&chess_move);
+            let score = self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            board.unmake_move(undo);
+            let score = -score?;
+            if score > best {
+                best = score;
+                best_move = chess_move;
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(chess_move);
+                    pv.append(&mut child_pv);
+                }
+            }
+            if alpha >= beta {
+                self.cutoffs.fetch_add(1, Ordering::Relaxed);
+                if index == 0 {
+                    self.first_move_cutoffs.fetch_add(1, Ordering::Relaxed);
+                }
+                self.ordering.cutoff(board, &chess_move, depth, ply);
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(
+            hash,
+            TableEntry {
+                depth,
+                bound,
+                score: score_to_table(best, ply),
+                // when every move failed low there is no telling which one is best
+                best_move: (bound != Bound::Upper).then_some(best_move),
+            },
+        );
+        Some(best)
+    }
+
+    /// Scores `board` like `negamax`, but only looks at captures and promotions, until none are left.
+    /// This way the model never judges a position in the middle of an exchange.
+    /// A side in check has to get out of it, so then every move is looked at.
+    fn quiescence(
+        &self,
+        board: &mut ChessBoard,
+        ply: u8,
+        plies: u8,
+        mut alpha: f64,
+        beta: f64,
+    ) -> Option<f64> {
+        if self.aborted() {
+            return None;
+        }
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.quiescence_nodes.fetch_add(1, Ordering::Relaxed);
+
+        if board.draw_by_rule().is_some() {
+            return Some(0.0);
+        }
+        let legal_moves = board.generate_legal_moves();
+        if legal_moves.is_empty() {
+            return Some(no_moves_score(board, ply));
+        }
+        if plies == 0 {
+            return Some(self.evaluate(board));
+        }
+        // the side to move doesn't have to capture, so the position is worth at least what it is now.
+        // In check it may well be worth less.
+        let in_check = board.is_in_check(board.current_player);
+        let stand_pat = if in_check {
+            f64::NEG_INFINITY
+        } else {
+            self.evaluate(board)
+        };
+        if stand_pat >= beta {
+            return Some(stand_pat);
+        }
+        alpha = alpha.max(stand_pat);
+
+        // the biggest gains first, trying a queen for a guarded pawn first grows the tree beyond all measure
+        let mut moves: Vec<(Move, f64)> = legal_moves
+            .into_iter()
+            .filter(|chess_move| {
+                in_check
+                    || board.captured_piece(chess_move).is_some()
+                    || board.promotion_piece(chess_move).is_some()
+            })
+            .map(|chess_move| (chess_move, self.model.move_gain(board, &chess_move)))
+            .collect();
+        moves.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let mut best = stand_pat;
+        for (chess_move, gain) in moves {
+            // delta pruning: the opponent may stand pat after the move, so it can't score more than
+            // what it changes on the board. If that isn't enough to raise alpha, no later move is either.
+            if !in_check && stand_pat + gain <= alpha {
+                break;
+            }
+            let undo = board.make_move(&chess_move);
+            let score = self.quiescence(board, ply + 1, plies - 1, -beta, -alpha);
+            board.unmake_move(undo);
+            let score = -score?;
+            if score > best {
+                best = score;
+                alpha = alpha.max(score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        Some(best)
+    }
+
+    /// The model's score of `board`, for the side to move
+    fn evaluate(&self, board: &ChessBoard) -> f64 {
+        self.model.grade_board(board)
+            * match board.current_player {
+                Color::Black => -1.0,
+                Color::White => 1.0,
+            }
+    }
+
+    /// Follows the best moves stored for `board` and the positions after them, at most `depth` half moves
+    fn line_from_table(&self, board: &ChessBoard, depth: u8, pv: &mut Vec<Move>) {
+        let mut board = board.clone();
+        while pv.len() < depth as usize {
+            let Some(chess_move) = self
+                .table
+                .probe(board.zobrist_hash())
+                .and_then(|entry| entry.best_move)
+                .filter(|chess_move| board.is_legal(chess_move))
+            else {
+                break;
+            };
+            board.play_move(&chess_move);
+            pv.push(chess_move);
+        }
+    }
+
+    /// Searches the root moves `depth` half moves deep, returning the best score and line.
+    /// `None` if the search was stopped before it could finish.
+    fn search_root(
+        &self,
+        board: &ChessBoard,
+        moves: &[Move],
+        depth: u8,
+    ) -> Option<(f64, Vec<Move>)> {
+        // the root moves are split between the threads, which share the best score so far
+        let best_score = AtomicU64::new(f64::NEG_INFINITY.to_bits());
+        let graded: Vec<(usize, f64, Vec<Move>)> = moves
+            .par_iter()
+            .enumerate()
+            // a single piece of work goes through the moves in order
+            .with_min_len(if self.parallel { 1 } else { usize::MAX })
+            .map_init(
+                || board.clone(),
+                |board, (index, chess_move)| {
+                    let alpha = f64::from_bits(best_score.load(Ordering::Relaxed));
+                    let undo = board.make_move(chess_move);
+                    let mut pv = Vec::new();
+                    let score =
+                        self.negamax(board, depth - 1, 1, f64::NEG_INFINITY, -alpha, &mut pv);
+                    board.unmake_move(undo);
+                    let score = -score?;
+                    best_score
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |best| {
+                            (score > f64::from_bits(best)).then_some(score.to_bits())
+                        })
+                        .ok();
+                    pv.insert(0, *chess_move);
+                    // otherwise the score is only a bound, and some other move is at least as good
+                    Some((
+                        index,
+                        if score > alpha {
+                            score
+                        } else {
+                            f64::NEG_INFINITY
+                        },
+                        pv,
+                    ))
+                },
+            )
+            .collect::<Option<_>>()?;
+
+        graded
+            .into_iter()
+            // ties go to the earlier move
+            .max_by(|(index_a, a, _), (index_b, b, _)| a.total_cmp(b).then(index_b.cmp(index_a)))
+            .map(|(_, score, pv)| (score, pv))
+    }
+}
+
+/// Score of a side without a legal move, `ply` half moves below the root
+fn no_moves_score(board: &ChessBoard, ply: u8) -> f64 {
+    if board.is_in_check(board.current_player) {
+        // the sooner the better for the side giving mate
+        -MATE_SCORE + ply.saturating_sub(1) as f64
+    } else {
+        0.0
+    }
+}
+
+/// The table keeps mate scores counted from the position they belong to, a search finds it at all sorts of plies
+fn score_to_table(score: f64, ply: u8) -> f64 {
+    if score >= MATE_THRESHOLD {
+        score + ply as f64
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as f64
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: f64, ply: u8) -> f64 {
+    if score >= MATE_THRESHOLD {
+        score - ply as f64
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as f64
+    } else {
+        score
+    }
+}
+
+impl Model {
+    /// Picks a move for the side to move, `None` if there is none.
+    /// Searches one half move deeper after the other until `limits` are reached, calling `report`
+    /// after each of them. Setting `stop` ends the search early, the last completed depth then decides the move.
+    /// Nothing carries over to the next move, `search_with_table` keeps a table between them.
+    pub fn search(
+        &self,
+        board: &ChessBoard,
+        limits: &SearchLimits,
+        stop: &AtomicBool,
+        report: impl FnMut(&SearchInfo),
+    ) -> Option<SearchInfo> {
+        let table = TranspositionTable::new(ONE_OFF_TABLE_MB);
+        self.search_with_table(board, limits, &table, stop, report)
+    }
+
+    /// Like `search`, but keeps what it finds out in `table`, where the next search can use it
+    pub fn search_with_table(
+        &self,
+        board: &ChessBoard,
+        limits: &SearchLimits,
+        table: &TranspositionTable,
+        stop: &AtomicBool,
+        report: impl FnMut(&SearchInfo),
+    ) -> Option<SearchInfo> {
+        self.run_search(board, limits, table, stop, true, report)
+    }
+
+    /// Like `search_with_table`, but on the calling thread alone.
+    /// Without a time limit the same position and table then always give the same move.
+    pub(super) fn search_on_one_thread(
+        &self,
+        board: &ChessBoard,
+        limits: &SearchLimits,
+        table: &TranspositionTable,
+        stop: &AtomicBool,
+    ) -> Option<SearchInfo> {
+        self.run_search(board, limits, table, stop, false, |_| {})
+    }
+
+    fn run_search(
+        &self,
+        board: &ChessBoard,
+        limits: &SearchLimits,
+        table: &TranspositionTable,
+        stop: &AtomicBool,
+        parallel: bool,
+        mut report: impl FnMut(&SearchInfo),
+    ) -> Option<SearchInfo> {
+        let start = Instant::now();
+        let time_budget = limits.time_budget();
+        let mut search = Search::new(
+            self,
+            table,
+            stop,
+            // too far off to be told as an instant is as good as never
+            time_budget.and_then(|(_, hard)| start.checked_add(hard)),
+            limits.nodes,
+        );
+        search.parallel = parallel;
+        let max_depth = match limits {
+            SearchLimits {
+                depth: Some(depth), ..
+            } => (*depth).max(1),
+            SearchLimits {
+                movetime: None,
+                clock: None,
+                nodes: None,
+                ..
+            } => self.depth + 1,
+            _ => u8::MAX,
+        };
+
+        let mut moves = board.get_legal_moves();
+        search.ordering.order(board, &mut moves, None, 0);
+        // stopped before even the first depth was done, any move is better than none
+        let mut best = SearchInfo {
+            depth: 0,
+            score: 0.0,
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: vec![*moves.first()?],
+            stats: SearchStats::default(),
+        };
+        for depth in 1..=max_depth {
+            let Some((score, pv)) = search.search_root(board, &moves, depth) else {
+                break;
+            };
+            // the best move so far is the most likely to be best again
+            let index = moves
+                .iter()
+                .position(|chess_move| *chess_move == pv[0])
+                .unwrap();
+            moves[..=index].rotate_right(1);
+
+            best = SearchInfo {
+                depth,
+                score,
+                nodes: search.nodes.load(Ordering::Relaxed),
+                time: start.elapsed(),
+                pv,
+                stats: search.stats(),
+            };
+            report(&best);
+            // looking deeper can't find a quicker mate or a way out
+            if best.mate_in().is_some()
+                || time_budget.is_some_and(|(soft, _)| start.elapsed() >= soft)
+            {
+                break;
+            }
+        }
+        best.nodes = search.nodes.load(Ordering::Relaxed);
+        best.time = start.elapsed();
+        best.stats = search.stats();
+        Some(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn search_to(model: &Model, board: &ChessBoard, depth: u8) -> SearchInfo {
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+        model
+            .search(board, &limits, &AtomicBool::new(false), |_| {})
+            .unwrap()
+    }
+
+    #[test]
+    fn search_respects_stop() {
+        let model = Model::new();
+        let board = ChessBoard::init_default();
+        let stop = AtomicBool::new(true);
+        let limits = SearchLimits {
+            depth: Some(8),
+            ..Default::default()
+        };
+        let info = model.search(&board, &limits, &stop, |_| {}).unwrap();
+        assert_eq!(info.nodes, 0);
+        assert!(board.is_legal(&info.pv[0]));
+    }
+
+    #[test]
+    fn deepens_one_half_move_at_a_time() {
+        let model = Model::new();
+        let board = ChessBoard::init_default();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let mut depths = Vec::new();
+        let info = model
+            .search(&board, &limits, &AtomicBool::new(false), |info| {
+                depths.push(info.depth)
+            })
+            .unwrap();
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(info.depth, 3);
+    }
+
+    #[test]
+    fn stops_at_the_node_limit() {
+        let model = Model::new();
+        let board = ChessBoard::init_default();
+        let limits = SearchLimits {
+            nodes: Some(3000),
+            ..Default::default()
+        };
+        let mut last_reported = None;
+        let info = model
+            .search(&board, &limits, &AtomicBool::new(false), |info| {
+                last_reported = Some(info.clone())
+            })
+            .unwrap();
+        let last_reported = last_reported.unwrap();
+        assert!(last_reported.nodes <= 3000);
+        // the unfinished depth doesn't count
+        assert_eq!(info.depth, last_reported.depth);
+        assert_eq!(info.pv, last_reported.pv);
+        assert!(info.nodes >= 3000);
+    }
+
+    #[test]
+    fn stops_at_the_move_time() {
+        let model = Model::new();
+        let board = ChessBoard::init_default();
+        let limits = SearchLimits {
+            movetime: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let info = model
+            .search(&board, &limits, &AtomicBool::new(false), |_| {})
+            .unwrap();
+        assert!(info.depth >= 1);
+        assert!(info.time < Duration::from_secs(1), "{:?}", info.time);
+    }
+
+    #[test]
+    fn budgets_the_clock() {
+        let limits = SearchLimits {
+            clock: Some(Clock {
+                remaining: Duration::from_secs(60),
+                increment: Duration::from_secs(1),
+                moves_to_go: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.time_budget(),
+            Some((Duration::from_millis(2750), Duration::from_millis(8250)))
+        );
+
+        let with_movetime = SearchLimits {
+            movetime: Some(Duration::from_secs(5)),
+            ..limits
+        };
+        assert_eq!(
+            with_movetime.time_budget(),
+            Some((Duration::from_millis(2750), Duration::from_secs(5)))
+        );
+
+        // a big increment doesn't make the search use up what is left
+        let short_on_time = SearchLimits {
+            clock: Some(Clock {
+                remaining: Duration::from_secs(1),
+                increment: Duration::from_secs(2),
+                moves_to_go: Some(10),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            short_on_time.time_budget(),
+            Some((Duration::from_millis(500), Duration::from_millis(500)))
+        );
+    }
+
+    #[test]
+    fn survives_endless_time() {
+        let endless = SearchLimits {
+            depth: Some(1),
+            movetime: Some(Duration::MAX),
+            clock: Some(Clock {
+                remaining: Duration::MAX,
+                increment: Duration::MAX,
+                moves_to_go: Some(1),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            endless.time_budget(),
+            Some((Duration::MAX / 2, Duration::MAX / 2))
+        );
+        let only_movetime = SearchLimits {
+            clock: None,
+            ..endless
+        };
+        assert_eq!(
+            only_movetime.time_budget(),
+            Some((Duration::MAX, Duration::MAX))
+        );
+
+        // no instant is that far off, so there is no deadline at all
+        let board = ChessBoard::init_default();
+        for limits in [endless, only_movetime] {
+            let info = Model::new()
+                .search(&board, &limits, &AtomicBool::new(false), |_| {})
+                .unwrap();
+            assert_eq!(info.depth, 1);
+        }
+    }
+
+    #[test]
+    fn alpha_beta_matches_full_search() {
+        let mut model = Model::new();
+        model.randomize_heat_maps(0.0, 1.0, &mut StdRng::seed_from_u64(0));
+        // a tactical position keeps the capture search busy, so it gets cross-checked too
+        let board = ChessBoard::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let info = search_to(&model, &board, 2);
+
+        // every root move with the full window
+        let (table, stop) = (TranspositionTable::default(), AtomicBool::new(false));
+        let full_search = Search::new(&model, &table, &stop, None, None);
+        let grades = full_search.grade_moves(&board, 2).unwrap();
+        let best = grades
+            .iter()
+            .map(|(_, score)| *score)
+            .max_by(f64::total_cmp)
+            .unwrap();
+        assert_eq!(info.score, best);
+        assert!(grades.contains(&(info.pv[0], best)));
+        // pruning has to pay off
+        let full_nodes = full_search.nodes.load(Ordering::Relaxed);
+        assert!(info.nodes < full_nodes, "{} {full_nodes}", info.nodes);
+    }
+
+    #[test]
+    fn ordering_finds_cutoffs_early() {
+        let mut model = Model::new();
+        model.randomize_heat_maps(0.0, 1.0, &mut StdRng::seed_from_u64(0));
+        let info = search_to(&model, &ChessBoard::init_default(), 4);
+        let stats = info.stats;
+        assert!(stats.quiescence_nodes < info.nodes);
+        assert!(stats.cutoffs > 0);
+        // a good first guess is what makes alpha-beta fast
+        assert!(
+            stats.first_move_cutoffs * 4 >= stats.cutoffs * 3,
+            "{stats:?}"
+        );
+    }
+
+    #[test]
+    fn pv_is_playable() {
+        let mut model = Model::new();
+        model.randomize_heat_maps(0.0, 1.0, &mut StdRng::seed_from_u64(0));
+        let mut board = ChessBoard::init_default();
+        let info = search_to(&model, &board, 4);
+        // lines cut short by the table may lose moves the table no longer has
+        assert!((1..=4).contains(&info.pv.len()), "{:?}", info.pv);
+        for chess_move in &info.pv {
+            assert!(board.play_move(chess_move));
+        }
+    }
+
+    #[test]
+    fn sees_the_recapture() {
+        let model = Model::new();
+        // Qxd5 wins a pawn, until exd5 wins the queen back
+        let board = ChessBoard::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let info = search_to(&model, &board, 1);
+        assert_ne!(info.pv[0], "d1d5".parse().unwrap());
+        assert_eq!(info.score, 9.0 - 2.0);
+
+        // with nothing guarding it, the pawn is free
+        let board = ChessBoard::from_fen("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let info = search_to(&model, &board, 1);
+        assert_eq!(info.pv[0], "d1d5".parse().unwrap());
+        assert_eq!(info.score, 9.0);
+    }
+
+    #[test]
+    fn prefers_the_quicker_mate() {
+        let model = Model::new();
+        // Qb8 mates at once, plenty of other moves mate a move later
+        let board = ChessBoard::from_fen("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let info = search_to(&model, &board, 3);
+        assert_eq!(info.score, MATE_SCORE);
+        assert_eq!(info.mate_in(), Some(1));
+        assert_eq!(info.pv.len(), 1);
+
+        // Kb8 is forced, then Rh8 mates
+        let board = ChessBoard::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+        let info = search_to(&model, &board, 4);
+        assert_eq!(info.mate_in(), Some(-1));
+    }
+
+    #[test]
+    fn sees_mate_and_stalemate_on_the_horizon() {
+        let model = Model::new();
+        let board = ChessBoard::from_fen("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        let info = search_to(&model, &board, 1);
+        assert_eq!(info.pv[0], "b1b8".parse().unwrap());
+        assert_eq!(info.mate_in(), Some(1));
+
+        // Qf7 stalemates, it isn't worth the queen it keeps
+        let board = ChessBoard::from_fen("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1").unwrap();
+        let (table, stop) = (TranspositionTable::default(), AtomicBool::new(false));
+        let grades = Search::new(&model, &table, &stop, None, None)
+            .grade_moves(&board, 1)
+            .unwrap();
+        assert!(grades.contains(&("f1f7".parse().unwrap(), 0.0)));
+        assert!(grades.contains(&("f1f8".parse().unwrap(), MATE_SCORE)));
+
+        // in check black can't stand pat, so the fork wins the rook
+        let board = ChessBoard::from_fen("r3k3/8/8/3N4/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let info = search_to(&model, &board, 1);
+        assert_eq!(info.pv[0], "d5c7".parse().unwrap());
+        assert_eq!(info.score, 4.0);
+    }
+}
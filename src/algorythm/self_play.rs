@@ -0,0 +1,171 @@
+use super::{Model, SearchLimits, TranspositionTable};
+use crate::{ChessBoard, Color, Game, GameResult, Move, PgnResult};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+
+/// How a game between two models ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// By the rules of chess
+    Finished(GameResult),
+    /// It went on longer than allowed, that counts as a draw
+    MoveLimit,
+    /// A side came up with no move or one that isn't allowed, and lost for it
+    Forfeit { loser: Color },
+}
+
+/// A game played by `SelfPlay`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    pub outcome: Outcome,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    pub fn winner(&self) -> Option<Color> {
+        match self.outcome {
+            Outcome::Finished(result) => result.winner(),
+            Outcome::MoveLimit => None,
+            Outcome::Forfeit { loser } => Some(!loser),
+        }
+    }
+
+    /// 1 for a win, 0.5 for a draw and 0 for a loss, the usual way to count points in a tournament
+    pub fn points_for(&self, color: Color) -> f64 {
+        match self.winner() {
+            Some(winner) if winner == color => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
+
+    pub fn pgn_result(&self) -> PgnResult {
+        match self.winner() {
+            Some(Color::White) => PgnResult::WhiteWins,
+            Some(Color::Black) => PgnResult::BlackWins,
+            None => PgnResult::Draw,
+        }
+    }
+}
+
+/// Lets two models play a whole game against each other, taking turns until it is over or too long
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SelfPlay {
+    /// Where the game starts
+    pub board: ChessBoard,
+    /// How hard both models think about every move
+    pub limits: SearchLimits,
+    /// Half moves after which the game is called a draw
+    pub max_plies: u32,
+    /// Size of the transposition table each side keeps for the whole game
+    pub table_mb: usize,
+}
+
+impl Default for SelfPlay {
+    fn default() -> Self {
+        Self {
+            board: ChessBoard::init_default(),
+            limits: SearchLimits {
+                depth: Some(2),
+                ..Default::default()
+            },
+            max_plies: 200,
+            table_mb: 1,
+        }
+    }
+}
+
+impl SelfPlay {
+    pub fn play(&self, white: &Model, black: &Model) -> GameRecord {
+        let mut game = Game::new(self.board.clone());
+        let white_table = TranspositionTable::new(self.table_mb);
+        let black_table = TranspositionTable::new(self.table_mb);
+        let stop = AtomicBool::new(false);
+
+        let outcome = loop {
+            if let Some(result) = game.result() {
+                break Outcome::Finished(result);
+            }
+            if game.moves().len() >= self.max_plies as usize {
+                break Outcome::MoveLimit;
+            }
+            let color = game.board().current_player;
+            let (model, table) = match color {
+                Color::White => (white, &white_table),
+                Color::Black => (black, &black_table),
+            };
+            // one thread per game, so the same models always play the same game
+            let chess_move = model
+                .search_on_one_thread(game.board(), &self.limits, table, &stop)
+                .map(|info| info.pv[0]);
+            if !chess_move.is_some_and(|chess_move| game.play_move(&chess_move)) {
+                break Outcome::Forfeit { loser: color };
+            }
+        };
+
+        GameRecord {
+            outcome,
+            moves: game.moves().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn self_play(fen: &str, max_plies: u32) -> SelfPlay {
+        SelfPlay {
+            board: ChessBoard::from_fen(fen).unwrap(),
+            max_plies,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stops_at_the_move_limit() {
+        let mut white = Model::default();
+        white.randomize_heat_maps(0.0, 1.0, &mut StdRng::seed_from_u64(0));
+        let record = self_play(ChessBoard::STARTING_FEN, 6).play(&white, &Model::default());
+        assert_eq!(record.outcome, Outcome::MoveLimit);
+        assert_eq!(record.points_for(Color::White), 0.5);
+        assert_eq!(record.pgn_result(), PgnResult::Draw);
+
+        // every move was allowed when it was played
+        let mut board = ChessBoard::init_default();
+        assert_eq!(record.moves.len(), 6);
+        for chess_move in &record.moves {
+            assert!(board.play_move(chess_move));
+        }
+    }
+
+    #[test]
+    fn plays_until_mate() {
+        // Qb8 mates
+        let record = self_play("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1", 10)
+            .play(&Model::default(), &Model::default());
+        assert_eq!(
+            record.outcome,
+            Outcome::Finished(GameResult::Checkmate {
+                winner: Color::White
+            })
+        );
+        assert_eq!(record.moves, ["b1b8".parse().unwrap()]);
+        assert_eq!(record.points_for(Color::White), 1.0);
+        assert_eq!(record.points_for(Color::Black), 0.0);
+        assert_eq!(record.pgn_result(), PgnResult::WhiteWins);
+    }
+
+    #[test]
+    fn finished_games_stay_finished() {
+        let record = self_play("8/8/8/4k3/8/8/8/4K3 w - - 0 1", 10)
+            .play(&Model::default(), &Model::default());
+        assert_eq!(
+            record.outcome,
+            Outcome::Finished(GameResult::InsufficientMaterial)
+        );
+        assert!(record.moves.is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+use super::{GameRecord, HeatMapMutation, Model, MutationError, SelfPlay};
+use crate::Color;
+use rand::distributions::WeightedIndex;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::Distribution;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Who plays whom to find out how good every model is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pairing {
+    /// Everybody plays everybody else twice, once with each color
+    RoundRobin,
+    /// Models with about the same points so far meet each other, far fewer games than a round robin
+    Swiss { rounds: u32 },
+}
+
+/// How the parents of the next generation are picked
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Selection {
+    /// The fittest of `size` models drawn at random
+    Tournament { size: usize },
+    /// At random, with chances in proportion to the fitness
+    Roulette,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrainingConfig {
+    /// Models per generation
+    pub population: usize,
+    pub pairing: Pairing,
+    pub selection: Selection,
+    /// How many of the fittest models make it into the next generation unchanged
+    pub elite: usize,
+    /// Spread of the heat maps of the first generation, around 0
+    pub initial_std_dev: f64,
+    /// How a child's tiles move away from its parents'
+    pub mutation: HeatMapMutation,
+    /// How far a child's piece values move away from its parents'
+    pub material_std_dev: f64,
+    /// How the games of the tournaments are played
+    pub self_play: SelfPlay,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            population: 16,
+            pairing: Pairing::Swiss { rounds: 5 },
+            selection: Selection::Tournament { size: 3 },
+            elite: 2,
+            initial_std_dev: 0.1,
+            mutation: HeatMapMutation::gaussian(0.02),
+            material_std_dev: 0.05,
+            self_play: SelfPlay::default(),
+        }
+    }
+}
+
+impl TrainingConfig {
+    /// Checks the settings a trainer can't work with
+    pub fn validate(&self) -> Result<(), TrainingConfigError> {
+        if self.population == 0 {
+            return Err(TrainingConfigError::EmptyPopulation);
+        }
+        for std_dev in [self.initial_std_dev, self.material_std_dev] {
+            if !(std_dev.is_finite() && std_dev >= 0.0) {
+                return Err(TrainingConfigError::StdDev(std_dev));
+            }
+        }
+        self.mutation.validate()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrainingConfigError {
+    /// Without models there is nobody to play or breed
+    EmptyPopulation,
+    /// A spread of the first generation or of the piece values that is negative or not finite
+    StdDev(f64),
+    Mutation(MutationError),
+}
+
+impl fmt::Display for TrainingConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyPopulation => write!(f, "the population needs at least one model"),
+            Self::StdDev(std_dev) => write!(f, "std dev {std_dev} isn't 0 or more"),
+            Self::Mutation(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for TrainingConfigError {}
+
+impl From<MutationError> for TrainingConfigError {
+    fn from(error: MutationError) -> Self {
+        Self::Mutation(error)
+    }
+}
+
+/// How one generation did in its tournament
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationStats {
+    /// Counting from 0
+    pub generation: u32,
+    /// Share of the possible points, from 0 to 1
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    pub worst_fitness: f64,
+    pub games: usize,
+    pub white_wins: usize,
+    pub black_wins: usize,
+    pub draws: usize,
+    /// Half moves per game
+    pub mean_plies: f64,
+    pub time: Duration,
+}
+
+/// Evolves a population of models: they play each other, the fittest become the parents of the next generation.
+/// All chance comes from the seed, two trainers with the same seed and settings breed the same models
+/// as long as the games aren't limited by time.
+#[derive(Serialize, Deserialize)]
+pub struct Trainer {
+    pub(super) config: TrainingConfig,
+    pub(super) population: Vec<Model>,
+    pub(super) generation: u32,
+    /// The fittest model of the last generation that played
+    pub(super) champion: Option<Model>,
+    pub(super) seed: u64,
+}
+
+impl Trainer {
+    /// A first generation with random heat maps, from a seed of its own
+    pub fn new(config: TrainingConfig) -> Result<Self, TrainingConfigError> {
+        Self::with_seed(config, rand::random())
+    }
+
+    /// A first generation with random heat maps, all of them following from `seed`
+    pub fn with_seed(config: TrainingConfig, seed: u64) -> Result<Self, TrainingConfigError> {
+        config.validate()?;
+        let mut rng = rng_for(seed, 0);
+        let population = (0..config.population)
+            .map(|_| {
+                let mut model = Model::default();
+                model.randomize_heat_maps(0.0, config.initial_std_dev, &mut rng);
+                model
+            })
+            .collect();
+        Ok(Self {
+            config,
+            population,
+            generation: 0,
+            champion: None,
+            seed,
+        })
+    }
+
+    pub fn config(&self) -> &TrainingConfig {
+        &self.config
+    }
+    pub fn population(&self) -> &[Model] {
+        &self.population
+    }
+    /// How many generations have been trained
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+    pub fn champion(&self) -> Option<&Model> {
+        self.champion.as_ref()
+    }
+    /// Where all the random numbers of the run come from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Trains `generations` generations, calling `report` after each of them
+    pub fn train(&mut self, generations: u32, mut report: impl FnMut(&GenerationStats)) {
+        for _ in 0..generations {
+            let stats = self.next_generation();
+            report(&stats);
+        }
+    }
+
+    /// Lets the current generation play its tournament and breeds the next one from the results
+    pub fn next_generation(&mut self) -> GenerationStats {
+        let start = Instant::now();
+        // every generation has numbers of its own, a resumed run goes on like it never stopped
+        let mut rng = rng_for(self.seed, self.generation + 1);
+        let (fitness, records) = self.tournament(&mut rng);
+
+        let mut ranking: Vec<usize> = (0..self.population.len()).collect();
+        ranking.sort_by(|&a, &b| fitness[b].total_cmp(&fitness[a]));
+
+        let mut next: Vec<Model> = ranking
+            .iter()
+            .take(self.config.elite)
+            .map(|&index| self.population[index].clone())
+            .collect();
+        while next.len() < self.config.population {
+            let mother = &self.population[self.select(&fitness, &mut rng)];
+            let father = &self.population[self.select(&fitness, &mut rng)];
+            let mut child = mother.breed_heat_maps(father, &mut rng);
+            child.mutate_heat_maps(&self.config.mutation, &mut rng);
+            child.mutate_material(self.config.material_std_dev, &mut rng);
+            next.push(child);
+        }
+
+        let stats = GenerationStats {
+            generation: self.generation,
+            best_fitness: fitness[ranking[0]],
+            mean_fitness: fitness.iter().sum::<f64>() / fitness.len() as f64,
+            worst_fitness: fitness[ranking[ranking.len() - 1]],
+            games: records.len(),
+            white_wins: count_winners(&records, Some(Color::White)),
+            black_wins: count_winners(&records, Some(Color::Black)),
+            draws: count_winners(&records, None),
+            mean_plies: records
+                .iter()
+                .map(|record| record.moves.len() as f64)
+                .sum::<f64>()
+                / records.len().max(1) as f64,
+            time: start.elapsed(),
+        };
+        self.champion = Some(self.population[ranking[0]].clone());
+        self.population = next;
+        self.generation += 1;
+        stats
+    }
+
+    /// Plays the games `pairing` asks for and returns the share of the possible points every model got,
+    /// together with all the games
+    fn tournament(&self, rng: &mut impl Rng) -> (Vec<f64>, Vec<GameRecord>) {
+        let len = self.population.len();
+        let mut points = vec![0.0; len];
+        let mut played = vec![0u32; len];
+        let mut records = Vec::new();
+
+        // sitting out a round counts as a won game, so nobody is worse off for it
+        let mut tally =
+            |round: Vec<(usize, usize, GameRecord)>, bye: Option<usize>, points: &mut Vec<f64>| {
+                if let Some(bye) = bye {
+                    points[bye] += 1.0;
+                    played[bye] += 1;
+                }
+                for (white, black, record) in round {
+                    points[white] += record.points_for(Color::White);
+                    points[black] += record.points_for(Color::Black);
+                    played[white] += 1;
+                    played[black] += 1;
+                    records.push(record);
+                }
+            };
+
+        match self.config.pairing {
+            Pairing::RoundRobin => {
+                let pairs = (0..len)
+                    .flat_map(|white| {
+                        (0..len)
+                            .filter(move |&black| black != white)
+                            .map(move |black| (white, black))
+                    })
+                    .collect();
+                tally(self.play_round(pairs), None, &mut points);
+            }
+            Pairing::Swiss { rounds } => {
+                let mut met = HashSet::new();
+                let mut byes = vec![0; len];
+                for round in 0..rounds {
+                    // the order of models with the same points is left to chance
+                    let mut standings: Vec<usize> = (0..len).collect();
+                    standings.shuffle(rng);
+                    standings.sort_by(|&a, &b| points[b].total_cmp(&points[a]));
+                    let (pairs, bye) = swiss_pairs(standings, &mut met, &mut byes, round);
+                    tally(self.play_round(pairs), bye, &mut points);
+                }
+            }
+        }
+
+        let fitness = points
+            .iter()
+            .zip(&played)
+            .map(|(points, &played)| points / played.max(1) as f64)
+            .collect();
+        (fitness, records)
+    }
+
+    /// Plays all games of `pairs`, white first, at the same time
+    fn play_round(&self, pairs: Vec<(usize, usize)>) -> Vec<(usize, usize, GameRecord)> {
+        pairs
+            .into_par_iter()
+            .map(|(white, black)| {
+                let record = self
+                    .config
+                    .self_play
+                    .play(&self.population[white], &self.population[black]);
+                (white, black, record)
+            })
+            .collect()
+    }
+
+    /// Index of a model to become a parent
+    fn select(&self, fitness: &[f64], rng: &mut impl Rng) -> usize {
+        match self.config.selection {
+            Selection::Tournament { size } => (0..size.max(1))
+                .map(|_| rng.gen_range(0..fitness.len()))
+                .max_by(|&a, &b| fitness[a].total_cmp(&fitness[b]))
+                .unwrap(),
+            // if nobody scored anything, everybody gets the same chance
+            Selection::Roulette => match WeightedIndex::new(fitness) {
+                Ok(weights) => weights.sample(rng),
+                Err(_) => rng.gen_range(0..fitness.len()),
+            },
+        }
+    }
+}
+
+/// How many steps `pairs_without_rematches` may take, it can take very long to find out there is no such pairing
+const PAIRING_TRIES: u32 = 10_000;
+
+/// Pairs the models of a round, from the top of the standings down, so nobody meets a model twice.
+/// If that can't be done, every model plays the next one below it that it hasn't met yet, or the next one.
+/// With an odd number of models the lowest one that sat out the fewest times sits out, `byes` counts them.
+/// The colors take turns from round to round.
+fn swiss_pairs(
+    mut standings: Vec<usize>,
+    met: &mut HashSet<(usize, usize)>,
+    byes: &mut [u32],
+    round: u32,
+) -> (Vec<(usize, usize)>, Option<usize>) {
+    let bye = (!standings.len().is_multiple_of(2)).then(|| {
+        let index = (0..standings.len())
+            .rev()
+            .min_by_key(|&index| byes[standings[index]])
+            .unwrap();
+        standings.remove(index)
+    });
+    if let Some(bye) = bye {
+        byes[bye] += 1;
+    }
+
+    let mut tries = PAIRING_TRIES;
+    let pairs = pairs_without_rematches(&standings, met, &mut tries)
+        .unwrap_or_else(|| nearest_pairs(standings, met));
+    let pairs = pairs
+        .into_iter()
+        .map(|(first, second)| {
+            met.insert(pair_key(first, second));
+            if round.is_multiple_of(2) {
+                (first, second)
+            } else {
+                (second, first)
+            }
+        })
+        .collect();
+    (pairs, bye)
+}
+
+/// Pairs `standings` from the top down without rematches, trying the nearest opponents first.
+/// `None` if there is no such pairing, or `tries` ran out before one was found.
+fn pairs_without_rematches(
+    standings: &[usize],
+    met: &HashSet<(usize, usize)>,
+    tries: &mut u32,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&first, rest)) = standings.split_first() else {
+        return Some(Vec::new());
+    };
+    for (index, &second) in rest.iter().enumerate() {
+        if met.contains(&pair_key(first, second)) {
+            continue;
+        }
+        *tries = tries.checked_sub(1)?;
+        let mut others = rest.to_vec();
+        others.remove(index);
+        if let Some(mut pairs) = pairs_without_rematches(&others, met, tries) {
+            pairs.insert(0, (first, second));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// Pairs every model, from the top of the standings down, with the next one below it that it hasn't met yet,
+/// or the next one if it has met everybody left
+fn nearest_pairs(mut standings: Vec<usize>, met: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    while standings.len() >= 2 {
+        let first = standings.remove(0);
+        let index = standings
+            .iter()
+            .position(|&other| !met.contains(&pair_key(first, other)))
+            .unwrap_or(0);
+        pairs.push((first, standings.remove(index)));
+    }
+    pairs
+}
+
+/// How `met` stores two models that played each other
+fn pair_key(first: usize, second: usize) -> (usize, usize) {
+    (first.min(second), first.max(second))
+}
+
+/// The random numbers that make generation `generation` of the run with `seed`.
+/// The generator is part of the checkpoint format, a recorded seed only means the same run with the same one.
+/// ChaCha8 is promised to give the same numbers on every platform and in every release of `rand_chacha`,
+/// `StdRng` may change with any release of `rand`.
+fn rng_for(seed: u64, generation: u32) -> ChaCha8Rng {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..12].copy_from_slice(&generation.to_le_bytes());
+    ChaCha8Rng::from_seed(bytes)
+}
+
+fn count_winners(records: &[GameRecord], winner: Option<Color>) -> usize {
+    records
+        .iter()
+        .filter(|record| record.winner() == winner)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChessPiece, GamePhase, SearchLimits};
+
+    fn config(pairing: Pairing, selection: Selection) -> TrainingConfig {
+        TrainingConfig {
+            population: 4,
+            pairing,
+            selection,
+            elite: 1,
+            self_play: SelfPlay {
+                limits: SearchLimits {
+                    depth: Some(1),
+                    ..Default::default()
+                },
+                max_plies: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_robin_plays_every_pairing() {
+        let mut trainer = Trainer::new(config(Pairing::RoundRobin, Selection::Roulette)).unwrap();
+        let stats = trainer.next_generation();
+        assert_eq!(stats.games, 4 * 3);
+        assert_eq!(
+            stats.white_wins + stats.black_wins + stats.draws,
+            stats.games
+        );
+        // nobody can win in two half moves
+        assert_eq!(stats.draws, stats.games);
+        assert_eq!(stats.mean_fitness, 0.5);
+        assert_eq!(stats.mean_plies, 2.0);
+    }
+
+    #[test]
+    fn generations_go_on() {
+        let mut trainer = Trainer::new(config(
+            Pairing::Swiss { rounds: 2 },
+            Selection::Tournament { size: 2 },
+        ))
+        .unwrap();
+        let mut reported = Vec::new();
+        trainer.train(2, |stats| reported.push(*stats));
+        assert_eq!(
+            reported
+                .iter()
+                .map(|stats| stats.generation)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        // two games a round
+        assert!(reported.iter().all(|stats| stats.games == 2 * 2));
+        assert_eq!(trainer.generation(), 2);
+        assert_eq!(trainer.population().len(), 4);
+
+        // the elite is passed on unchanged
+        let champion = trainer.champion().unwrap();
+        let heat_map = |model: &Model| {
+            model
+                .get_heat_map_for(ChessPiece::Knight, Color::White, GamePhase::Middlegame)
+                .clone()
+        };
+        assert!(trainer
+            .population()
+            .iter()
+            .any(|model| heat_map(model) == heat_map(champion)));
+    }
+
+    #[test]
+    fn selection_prefers_the_fit() {
+        let trainer = Trainer::new(config(
+            Pairing::RoundRobin,
+            Selection::Tournament { size: 20 },
+        ))
+        .unwrap();
+        let fitness = [0.1, 0.9, 0.5, 0.0];
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        // out of 20 draws the best is as good as always among them
+        let picks: Vec<usize> = (0..50)
+            .map(|_| trainer.select(&fitness, &mut rng))
+            .collect();
+        assert!(picks.iter().filter(|&&pick| pick == 1).count() >= 45);
+
+        let trainer = Trainer::new(config(Pairing::RoundRobin, Selection::Roulette)).unwrap();
+        let picks: Vec<usize> = (0..50)
+            .map(|_| trainer.select(&fitness, &mut rng))
+            .collect();
+        assert!(!picks.contains(&3));
+        // without any points it still picks somebody
+        trainer.select(&[0.0; 4], &mut rng);
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let mut met = HashSet::new();
+        let mut byes = [0; 5];
+        let pairs = |standings, met: &mut _, byes: &mut _, round| {
+            swiss_pairs(standings, met, byes, round).0
+        };
+        assert_eq!(
+            pairs(vec![0, 1, 2, 3], &mut met, &mut byes, 0),
+            [(0, 1), (2, 3)]
+        );
+        // the leaders have met, so both play the next one down, with the colors the other way round
+        assert_eq!(
+            pairs(vec![0, 1, 2, 3], &mut met, &mut byes, 1),
+            [(2, 0), (3, 1)]
+        );
+        assert_eq!(
+            pairs(vec![3, 0, 1, 2, 4], &mut met, &mut byes, 2),
+            [(3, 0), (1, 2)]
+        );
+        // everybody has met everybody, so neighbours play again
+        assert_eq!(
+            pairs(vec![0, 1, 2, 3], &mut met, &mut byes, 3),
+            [(1, 0), (3, 2)]
+        );
+
+        // taking the nearest opponent would leave 2 and 3, who have met, for the end
+        let mut met = HashSet::from([(0, 2), (2, 3)]);
+        assert_eq!(
+            pairs(vec![0, 1, 2, 3], &mut met, &mut [0; 4], 0),
+            [(0, 3), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn swiss_byes_go_round() {
+        let mut met = HashSet::new();
+        let mut byes = [0; 3];
+        let mut sat_out = Vec::new();
+        for round in 0..3 {
+            let (pairs, bye) = swiss_pairs(vec![0, 1, 2], &mut met, &mut byes, round);
+            assert_eq!(pairs.len(), 1);
+            sat_out.push(bye.unwrap());
+        }
+        // the last sits out first, then the lowest that hasn't yet
+        assert_eq!(sat_out, [2, 1, 0]);
+        assert_eq!(byes, [1, 1, 1]);
+        assert_eq!(swiss_pairs(vec![0, 1], &mut met, &mut byes, 3).1, None);
+
+        // the two that play stop after two plies and draw, the third wins its bye
+        let trainer = Trainer::with_seed(
+            TrainingConfig {
+                population: 3,
+                ..config(Pairing::Swiss { rounds: 1 }, Selection::Roulette)
+            },
+            0,
+        )
+        .unwrap();
+        let (mut fitness, records) = trainer.tournament(&mut rng_for(0, 0));
+        assert_eq!(records.len(), 1);
+        fitness.sort_by(f64::total_cmp);
+        assert_eq!(fitness, [0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn rejects_unusable_settings() {
+        let empty = TrainingConfig {
+            population: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Trainer::new(empty).err(),
+            Some(TrainingConfigError::EmptyPopulation)
+        );
+
+        let mut noisy = config(Pairing::RoundRobin, Selection::Roulette);
+        noisy.mutation.smoothing = 2.0;
+        assert_eq!(
+            Trainer::new(noisy).err(),
+            Some(TrainingConfigError::Mutation(MutationError::Smoothing(2.0)))
+        );
+        let wild = TrainingConfig {
+            initial_std_dev: f64::INFINITY,
+            ..Default::default()
+        };
+        assert_eq!(
+            Trainer::new(wild).err(),
+            Some(TrainingConfigError::StdDev(f64::INFINITY))
+        );
+    }
+
+    #[test]
+    fn same_seed_same_models() {
+        let mut config = config(
+            Pairing::Swiss { rounds: 2 },
+            Selection::Tournament { size: 2 },
+        );
+        config.self_play.max_plies = 6;
+        let run = |seed| {
+            let mut trainer = Trainer::with_seed(config.clone(), seed).unwrap();
+            let mut reported = Vec::new();
+            trainer.train(2, |stats| {
+                reported.push((stats.best_fitness, stats.mean_plies))
+            });
+            (bincode::serialize(trainer.population()).unwrap(), reported)
+        };
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5).0, run(6).0);
+
+        // recorded seeds have to keep giving the same numbers
+        assert_eq!(rng_for(5, 1).gen::<u64>(), 13246359517626681693);
+    }
+}
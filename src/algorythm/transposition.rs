@@ -0,0 +1,235 @@
+use crate::{BoardPosition, ChessPiece, Move};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Size of the table a search gets when nobody hands it one
+pub const DEFAULT_TABLE_MB: usize = 16;
+
+/// Size of the table of a search that is thrown away afterwards, a bigger one only costs the time to clear it
+pub(super) const ONE_OFF_TABLE_MB: usize = 1;
+
+/// How a stored score relates to the real score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The search looked at every move, this is the score
+    Exact,
+    /// A move was good enough for a cutoff, the score is at least this
+    Lower,
+    /// No move reached alpha, the score is at most this
+    Upper,
+}
+
+/// What an earlier search found out about a position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableEntry {
+    /// Half moves the position was searched
+    pub depth: u8,
+    pub bound: Bound,
+    pub score: f64,
+    /// `None` if no move stood out, like when all of them failed low
+    pub best_move: Option<Move>,
+}
+
+/// One slot of the table. `check` is the position's hash xored with the other two words, so an entry
+/// torn by two threads writing at once no longer matches its position and is treated as a miss.
+#[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    score: AtomicU64,
+    meta: AtomicU64,
+}
+
+/// Remembers search results by Zobrist hash, so a position reached again is not searched again.
+/// It is fixed size and lock free, every thread of a search reads and writes it through a shared reference.
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// A table taking up about `megabytes`, rounded down to a power of two entries
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = (megabytes << 20) / std::mem::size_of::<Slot>();
+        let len = match wanted {
+            0 => 1,
+            wanted => 1 << wanted.ilog2(),
+        };
+        Self {
+            slots: (0..len).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    /// Forgets everything, for a new game
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.score.store(0, Ordering::Relaxed);
+            slot.meta.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, hash: u64) -> &Slot {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = self.slot(hash);
+        let check = slot.check.load(Ordering::Relaxed);
+        let score = slot.score.load(Ordering::Relaxed);
+        let meta = slot.meta.load(Ordering::Relaxed);
+        if meta & VALID == 0 || check != hash ^ score ^ meta {
+            return None;
+        }
+        Some(unpack(f64::from_bits(score), meta))
+    }
+
+    /// Keeps the entry unless the slot holds a deeper search of the same position
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        let slot = self.slot(hash);
+        if let Some(old) = self.probe(hash) {
+            if old.depth > entry.depth {
+                return;
+            }
+        }
+        let score = entry.score.to_bits();
+        let meta = pack(&entry);
+        slot.check.store(hash ^ score ^ meta, Ordering::Relaxed);
+        slot.score.store(score, Ordering::Relaxed);
+        slot.meta.store(meta, Ordering::Relaxed);
+    }
+}
+
+// Layout of the meta word, from the lowest bit: depth (8), bound (2), from (6), to (6),
+// promotion (3, 0 for none), then a flag for having a move and one for the slot being used
+const VALID: u64 = 1 << 32;
+const HAS_MOVE: u64 = 1 << 31;
+
+fn pack(entry: &TableEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let mut meta = VALID | entry.depth as u64 | bound << 8;
+    if let Some(chess_move) = entry.best_move {
+        meta |= HAS_MOVE | pack_move(&chess_move) << 10;
+    }
+    meta
+}
+
+fn unpack(score: f64, meta: u64) -> TableEntry {
+    TableEntry {
+        depth: meta as u8,
+        bound: match meta >> 8 & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        },
+        score,
+        best_move: (meta & HAS_MOVE != 0).then(|| unpack_move(meta >> 10)),
+    }
+}
+
+/// Fits a move into the lowest 15 bits: from (6), to (6) and promotion (3, 0 for none)
+pub(super) fn pack_move(chess_move: &Move) -> u64 {
+    let square = |position: BoardPosition| (position.y * 8 + position.x) as u64;
+    let promotion = chess_move.promotion.map_or(0, |piece| piece as u64 + 1);
+    square(chess_move.from) | square(chess_move.to) << 6 | promotion << 12
+}
+
+/// Reads a move written by `pack_move`, ignoring any higher bits
+pub(super) fn unpack_move(bits: u64) -> Move {
+    let square = |bits: u64| BoardPosition::from_idx((bits >> 3 & 7) as usize, (bits & 7) as usize);
+    Move {
+        from: square(bits),
+        to: square(bits >> 6),
+        promotion: match bits >> 12 & 7 {
+            0 => None,
+            piece => Some(ChessPiece::ALL[piece as usize - 1]),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn entries_round_trip() {
+        let table = TranspositionTable::new(1);
+        let entries = [
+            TableEntry {
+                depth: 7,
+                bound: Bound::Lower,
+                score: -2.5,
+                best_move: Some("e7e8n".parse().unwrap()),
+            },
+            TableEntry {
+                depth: 0,
+                bound: Bound::Exact,
+                score: 0.1,
+                best_move: Some("h1a8".parse().unwrap()),
+            },
+            TableEntry {
+                depth: u8::MAX,
+                bound: Bound::Upper,
+                score: f64::NEG_INFINITY,
+                best_move: None,
+            },
+        ];
+        for (hash, entry) in entries.into_iter().enumerate() {
+            table.store(hash as u64, entry);
+            assert_eq!(table.probe(hash as u64), Some(entry));
+        }
+        // the same slot, but another position
+        assert_eq!(table.probe(1 << 40), None);
+
+        table.clear();
+        assert_eq!(table.probe(0), None);
+    }
+
+    #[test]
+    fn deeper_entries_stay() {
+        let table = TranspositionTable::new(1);
+        let entry = |depth| TableEntry {
+            depth,
+            bound: Bound::Exact,
+            score: depth as f64,
+            best_move: None,
+        };
+        table.store(42, entry(5));
+        table.store(42, entry(3));
+        assert_eq!(table.probe(42), Some(entry(5)));
+        table.store(42, entry(6));
+        assert_eq!(table.probe(42), Some(entry(6)));
+    }
+
+    #[test]
+    fn threads_never_read_torn_entries() {
+        let table = TranspositionTable::new(1);
+        // every thread writes entries only valid for its own hash into the same slot
+        (0..8u64).into_par_iter().for_each(|thread| {
+            let hash = thread << 40;
+            for round in 0..10_000u64 {
+                let score = (thread * 1_000_000 + round) as f64;
+                table.store(
+                    hash,
+                    TableEntry {
+                        depth: u8::MAX,
+                        bound: Bound::Exact,
+                        score,
+                        best_move: None,
+                    },
+                );
+                if let Some(entry) = table.probe(hash) {
+                    assert_eq!((entry.score as u64) / 1_000_000, thread);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,54 @@
+use project_smartypants::{ChessBoard, Model, SearchLimits, TranspositionTable};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// Quiet, tactical and endgame positions, to see how a change to the search plays out across them
+const POSITIONS: [&str; 5] = [
+    ChessBoard::STARTING_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+/// Searches every position to a fixed depth, the first argument, and prints where the nodes went
+fn main() {
+    let depth = std::env::args()
+        .nth(1)
+        .map(|depth| depth.parse().expect("the depth has to be a number"))
+        .unwrap_or(4);
+    let model = Model::default();
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..Default::default()
+    };
+
+    let (mut nodes, mut time) = (0, Duration::ZERO);
+    for fen in POSITIONS {
+        let board = ChessBoard::from_fen(fen).unwrap();
+        // a full size table, empty for every position
+        let table = TranspositionTable::default();
+        let info = model
+            .search_with_table(&board, &limits, &table, &AtomicBool::new(false), |_| {})
+            .unwrap();
+        let stats = info.stats;
+        println!("{fen}");
+        println!(
+            "  best {} nodes {} quiescence {} table hits {} cutoffs {} first move {:.1}% time {} ms",
+            info.pv[0],
+            info.nodes,
+            stats.quiescence_nodes,
+            stats.table_hits,
+            stats.cutoffs,
+            stats.first_move_cutoffs as f64 * 100.0 / stats.cutoffs.max(1) as f64,
+            info.time.as_millis()
+        );
+        nodes += info.nodes;
+        time += info.time;
+    }
+    println!(
+        "total nodes {nodes} time {} ms nps {:.0}",
+        time.as_millis(),
+        nodes as f64 / time.as_secs_f64()
+    );
+}
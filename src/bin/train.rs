@@ -0,0 +1,63 @@
+use project_smartypants::{Trainer, TrainingConfig};
+use std::path::PathBuf;
+
+/// Evolves a population of models for as many generations as the first argument says, printing how each one did.
+/// With a checkpoint file as the second argument, the state is saved there after every generation,
+/// and a run that finds the file already there picks up where it left off.
+/// A new run starts from the seed in the third argument, or a random one.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let generations: u32 = args
+        .next()
+        .map(|generations| {
+            generations
+                .parse()
+                .expect("the number of generations has to be a number")
+        })
+        .unwrap_or(10);
+    let checkpoint = args.next().map(PathBuf::from);
+    let seed: Option<u64> = args
+        .next()
+        .map(|seed| seed.parse().expect("the seed has to be a number"));
+
+    let mut trainer = match &checkpoint {
+        Some(path) if path.exists() => {
+            let trainer = Trainer::load(path).expect("the checkpoint can't be read");
+            println!(
+                "resuming {} at generation {}",
+                path.display(),
+                trainer.generation()
+            );
+            trainer
+        }
+        _ => {
+            let trainer = match seed {
+                Some(seed) => Trainer::with_seed(TrainingConfig::default(), seed),
+                None => Trainer::new(TrainingConfig::default()),
+            }
+            .expect("the default settings are fine");
+            println!("new run with seed {}", trainer.seed());
+            trainer
+        }
+    };
+
+    for _ in 0..generations {
+        let stats = trainer.next_generation();
+        println!(
+            "generation {} fitness best {:.3} mean {:.3} worst {:.3} games {} (+{} -{} ={}) plies {:.1} time {} ms",
+            stats.generation,
+            stats.best_fitness,
+            stats.mean_fitness,
+            stats.worst_fitness,
+            stats.games,
+            stats.white_wins,
+            stats.black_wins,
+            stats.draws,
+            stats.mean_plies,
+            stats.time.as_millis()
+        );
+        if let Some(path) = &checkpoint {
+            trainer.save(path).expect("the checkpoint can't be written");
+        }
+    }
+}
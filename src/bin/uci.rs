@@ -0,0 +1,7 @@
+use project_smartypants::{Model, Uci};
+use std::io;
+
+fn main() -> io::Result<()> {
+    let mut uci = Uci::new(Model::default(), io::stdout());
+    uci.run(io::stdin().lock())
+}
@@ -0,0 +1,7 @@
+use project_smartypants::{Model, Xboard};
+use std::io;
+
+fn main() -> io::Result<()> {
+    let mut xboard = Xboard::new(Model::default(), io::stdout());
+    xboard.run(io::stdin().lock())
+}
@@ -0,0 +1,629 @@
+use super::zobrist::{castling_key, en_passant_key, piece_key, side_key};
+use super::{
+    BoardPosition, CastlingRights, ChessBoard, ChessPiece, Color, FenError, GameResult, Move,
+    KNIGHT_JUMPS,
+};
+
+/// Steps of the king, and the directions pieces slide in. The first four lead to higher squares.
+const DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (-1, 1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (1, -1),
+];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+const KNIGHT_ATTACKS: [u64; 64] = step_attacks(&KNIGHT_JUMPS);
+const KING_ATTACKS: [u64; 64] = step_attacks(&DIRECTIONS);
+/// The squares a pawn captures on, by color. White pawns move towards row 0.
+const PAWN_ATTACKS: [[u64; 64]; 2] = [
+    step_attacks(&[(-1, -1), (1, -1)]),
+    step_attacks(&[(-1, 1), (1, 1)]),
+];
+/// Everything from a square to the edge of the board, by direction
+const RAYS: [[u64; 64]; 8] = rays();
+/// The squares with an even `x + y`, a1 is dark, so these are the light ones
+const LIGHT_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+
+const fn step_attacks(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut attacks = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut step = 0;
+        while step < steps.len() {
+            let x = (square % 8) as i8 + steps[step].0;
+            let y = (square / 8) as i8 + steps[step].1;
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                attacks[square] |= 1 << (y * 8 + x);
+            }
+            step += 1;
+        }
+        square += 1;
+    }
+    attacks
+}
+
+const fn rays() -> [[u64; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let (mut x, mut y) = ((square % 8) as i8, (square / 8) as i8);
+            loop {
+                x += DIRECTIONS[direction].0;
+                y += DIRECTIONS[direction].1;
+                if x < 0 || x >= 8 || y < 0 || y >= 8 {
+                    break;
+                }
+                rays[direction][square] |= 1 << (y * 8 + x);
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+/// The squares reached from `square` in `direction`, up to and including the first occupied one
+fn ray_attacks(square: usize, occupied: u64, direction: usize) -> u64 {
+    let ray = RAYS[direction][square];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    // the nearest blocker is the lowest square on rays going up, the highest on the others
+    let blocker = if direction < 4 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+fn slider_attacks(square: usize, occupied: u64, directions: &[usize]) -> u64 {
+    directions.iter().fold(0, |attacks, &direction| {
+        attacks | ray_attacks(square, occupied, direction)
+    })
+}
+
+fn square_of(position: &BoardPosition) -> usize {
+    position.y as usize * 8 + position.x as usize
+}
+
+fn position_of(square: usize) -> BoardPosition {
+    BoardPosition::from_idx(square / 8, square % 8)
+}
+
+/// The squares set in `bits`, lowest first
+fn squares(mut bits: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let square = bits.trailing_zeros() as usize;
+        bits &= bits.checked_sub(1)?;
+        Some(square)
+    })
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+/// The same position as a `ChessBoard`, but every piece type of every color is a `u64` with one bit per square
+/// (`y * 8 + x`). Copying it is cheap and attacks come from lookup tables instead of walking the board,
+/// so move generation is a lot faster. `From` converts between the two without losing anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitBoard {
+    /// By piece, then color
+    pieces: [[u64; 2]; 6],
+    /// Everything of one color
+    colors: [u64; 2],
+    pub current_player: Color,
+    pub castling_rights: CastlingRights,
+    pub en_passant: Option<BoardPosition>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    zobrist_hash: u64,
+}
+
+impl From<&ChessBoard> for BitBoard {
+    fn from(board: &ChessBoard) -> Self {
+        let mut bit_board = BitBoard {
+            pieces: [[0; 2]; 6],
+            colors: [0; 2],
+            current_player: board.current_player,
+            castling_rights: board.castling_rights,
+            en_passant: board.en_passant,
+            halfmove_clock: board.halfmove_clock,
+            fullmove_number: board.fullmove_number,
+            zobrist_hash: board.zobrist_hash,
+        };
+        for (piece, color, position) in board.get_all_pieces_and_positions() {
+            let bit = 1 << square_of(&position);
+            bit_board.pieces[piece as usize][color_index(color)] |= bit;
+            bit_board.colors[color_index(color)] |= bit;
+        }
+        bit_board
+    }
+}
+
+impl From<&BitBoard> for ChessBoard {
+    fn from(bit_board: &BitBoard) -> Self {
+        let mut board = ChessBoard::new();
+        for (piece, color, position) in bit_board.get_all_pieces_and_positions() {
+            board.fields[position.get_idx()] = Some((piece, color));
+        }
+        board.current_player = bit_board.current_player;
+        board.castling_rights = bit_board.castling_rights;
+        board.en_passant = bit_board.en_passant;
+        board.halfmove_clock = bit_board.halfmove_clock;
+        board.fullmove_number = bit_board.fullmove_number;
+        board.zobrist_hash = bit_board.zobrist_hash;
+        board
+    }
+}
+
+impl BitBoard {
+    pub fn init_default() -> Self {
+        Self::from(&ChessBoard::init_default())
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        ChessBoard::from_fen(fen).map(|board| Self::from(&board))
+    }
+
+    pub fn to_fen(&self) -> String {
+        ChessBoard::from(self).to_fen()
+    }
+
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    /// Every square with a `piece` of `color` on it
+    pub fn pieces(&self, piece: ChessPiece, color: Color) -> u64 {
+        self.pieces[piece as usize][color_index(color)]
+    }
+
+    pub fn occupied(&self) -> u64 {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn get_piece_at_position(&self, position: &BoardPosition) -> Option<(ChessPiece, Color)> {
+        self.piece_at(square_of(position))
+    }
+
+    fn piece_at(&self, square: usize) -> Option<(ChessPiece, Color)> {
+        let bit = 1 << square;
+        let color = [Color::White, Color::Black]
+            .into_iter()
+            .find(|&color| self.colors[color_index(color)] & bit != 0)?;
+        let piece = ChessPiece::ALL
+            .into_iter()
+            .find(|&piece| self.pieces(piece, color) & bit != 0)?;
+        Some((piece, color))
+    }
+
+    /// In the same order as `ChessBoard::get_all_pieces_and_positions`, row by row from the top
+    pub fn get_all_pieces_and_positions(&self) -> Vec<(ChessPiece, Color, BoardPosition)> {
+        squares(self.occupied())
+            .filter_map(|square| {
+                self.piece_at(square)
+                    .map(|(piece, color)| (piece, color, position_of(square)))
+            })
+            .collect()
+    }
+
+    fn toggle(&mut self, piece: ChessPiece, color: Color, square: usize) {
+        let bit = 1 << square;
+        self.pieces[piece as usize][color_index(color)] ^= bit;
+        self.colors[color_index(color)] ^= bit;
+        self.zobrist_hash ^= piece_key(piece, color, &position_of(square));
+    }
+
+    /// Checks if any piece of color `by` could capture on `position`, like `ChessBoard::is_attacked`
+    pub fn is_attacked(&self, position: &BoardPosition, by: Color) -> bool {
+        let square = square_of(position);
+        let occupied = self.occupied();
+        let queens = self.pieces(ChessPiece::Queen, by);
+        // a pawn of `by` attacks the square if a pawn of the other color there would attack the pawn
+        PAWN_ATTACKS[color_index(!by)][square] & self.pieces(ChessPiece::Pawn, by) != 0
+            || KNIGHT_ATTACKS[square] & self.pieces(ChessPiece::Knight, by) != 0
+            || KING_ATTACKS[square] & self.pieces(ChessPiece::King, by) != 0
+            || slider_attacks(square, occupied, &ROOK_DIRECTIONS)
+                & (self.pieces(ChessPiece::Rook, by) | queens)
+                != 0
+            || slider_attacks(square, occupied, &BISHOP_DIRECTIONS)
+                & (self.pieces(ChessPiece::Bishoph, by) | queens)
+                != 0
+    }
+
+    pub fn get_king_position(&self, color: Color) -> Option<BoardPosition> {
+        squares(self.pieces(ChessPiece::King, color))
+            .next()
+            .map(position_of)
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        self.get_king_position(color)
+            .is_some_and(|king| self.is_attacked(&king, !color))
+    }
+
+    /// All moves the current player is allowed to make. Promotions are listed once per piece.
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        let color = self.current_player;
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|chess_move| {
+                let mut moved_board = *self;
+                moved_board.apply_move_unchecked(chess_move);
+                !moved_board.is_in_check(color)
+            })
+            .collect()
+    }
+
+    /// The moves the pieces can make, whether they leave the own king in check or not
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let color = self.current_player;
+        let own = self.colors[color_index(color)];
+        let enemy = self.colors[color_index(!color)];
+        let occupied = own | enemy;
+        let mut moves = Vec::new();
+
+        for from in squares(own) {
+            let Some((piece, _)) = self.piece_at(from) else {
+                continue;
+            };
+            let targets = match piece {
+                ChessPiece::Pawn => self.pawn_targets(from, color, enemy, occupied),
+                ChessPiece::Knight => KNIGHT_ATTACKS[from],
+                ChessPiece::King => KING_ATTACKS[from] | self.castling_targets(from, color),
+                ChessPiece::Rook => slider_attacks(from, occupied, &ROOK_DIRECTIONS),
+                ChessPiece::Bishoph => slider_attacks(from, occupied, &BISHOP_DIRECTIONS),
+                ChessPiece::Queen => {
+                    slider_attacks(from, occupied, &ROOK_DIRECTIONS)
+                        | slider_attacks(from, occupied, &BISHOP_DIRECTIONS)
+                }
+            } & !own;
+
+            let from = position_of(from);
+            for to in squares(targets).map(position_of) {
+                if piece == ChessPiece::Pawn && to.forward(&color, 1).is_none() {
+                    moves.extend(
+                        ChessPiece::PROMOTION_CHOICES
+                            .iter()
+                            .map(|&promotion| Move::promoting(from, to, promotion)),
+                    );
+                } else {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+        moves
+    }
+
+    fn pawn_targets(&self, from: usize, color: Color, enemy: u64, occupied: u64) -> u64 {
+        let position = position_of(from);
+        let mut targets = PAWN_ATTACKS[color_index(color)][from] & enemy;
+        if let Some(en_passant) = self.en_passant {
+            // only if there is a pawn to take, right behind the square it skipped
+            let skipped_by_enemy = en_passant.backward(&color, 1).is_some_and(|pawn| {
+                self.get_piece_at_position(&pawn) == Some((ChessPiece::Pawn, !color))
+            });
+            if skipped_by_enemy {
+                targets |= PAWN_ATTACKS[color_index(color)][from] & 1 << square_of(&en_passant);
+            }
+        }
+        if let Some(forward) = position.forward(&color, 1) {
+            if occupied & 1 << square_of(&forward) == 0 {
+                targets |= 1 << square_of(&forward);
+                let start_row = CastlingRights::home_row(color).abs_diff(1);
+                if let Some(two_forward) = position.forward(&color, 2) {
+                    if position.y == start_row && occupied & 1 << square_of(&two_forward) == 0 {
+                        targets |= 1 << square_of(&two_forward);
+                    }
+                }
+            }
+        }
+        targets
+    }
+
+    /// The squares the king on `from` can castle to, with the same conditions as `ChessBoard`
+    fn castling_targets(&self, from: usize, color: Color) -> u64 {
+        let home_row = CastlingRights::home_row(color);
+        let king = position_of(from);
+        if king.y != home_row || king.x != 4 || self.is_attacked(&king, !color) {
+            return 0;
+        }
+        let square = |x: u8| BoardPosition { x, y: home_row };
+        let can_castle = |rook_x: u8, empty_xs: &[u8], safe_xs: &[u8]| {
+            self.get_piece_at_position(&square(rook_x)) == Some((ChessPiece::Rook, color))
+                && empty_xs
+                    .iter()
+                    .all(|&x| self.get_piece_at_position(&square(x)).is_none())
+                && safe_xs
+                    .iter()
+                    .all(|&x| !self.is_attacked(&square(x), !color))
+        };
+        let mut targets = 0;
+        if self.castling_rights.kingside(color) && can_castle(7, &[5, 6], &[5, 6]) {
+            targets |= 1 << square_of(&square(6));
+        }
+        if self.castling_rights.queenside(color) && can_castle(0, &[1, 2, 3], &[2, 3]) {
+            targets |= 1 << square_of(&square(2));
+        }
+        targets
+    }
+
+    /// Like `ChessBoard::is_legal`, a pawn reaching the last row without a promotion piece becomes a queen
+    pub fn is_legal(&self, chess_move: &Move) -> bool {
+        let chess_move = Move {
+            promotion: chess_move
+                .promotion
+                .or_else(|| self.promotion_piece(chess_move)),
+            ..*chess_move
+        };
+        self.get_legal_moves().contains(&chess_move)
+    }
+
+    /// Plays `chess_move` if it is allowed
+    pub fn play_move(&mut self, chess_move: &Move) -> bool {
+        if !self.is_legal(chess_move) {
+            return false;
+        }
+        self.apply_move_unchecked(chess_move);
+        true
+    }
+
+    /// Moves the piece and takes care of everything that comes with it, like `ChessBoard` does
+    fn apply_move_unchecked(&mut self, chess_move: &Move) {
+        let (from, to) = (square_of(&chess_move.from), square_of(&chess_move.to));
+        let Some((piece, color)) = self.piece_at(from) else {
+            return;
+        };
+        let captured = self.captured_piece(chess_move);
+        let promotion = self.promotion_piece(chess_move);
+
+        if captured.is_some() || piece == ChessPiece::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if let Some((captured, position)) = captured {
+            self.toggle(captured, !color, square_of(&position));
+        }
+        self.toggle(piece, color, from);
+        self.toggle(promotion.unwrap_or(piece), color, to);
+
+        // a king moving two squares is castling, the rook jumps over it
+        if piece == ChessPiece::King && chess_move.from.x.abs_diff(chess_move.to.x) == 2 {
+            let row = chess_move.from.y as usize * 8;
+            let (rook_from, rook_to) = if to > from { (7, 5) } else { (0, 3) };
+            self.toggle(ChessPiece::Rook, color, row + rook_from);
+            self.toggle(ChessPiece::Rook, color, row + rook_to);
+        }
+
+        let state_key = |board: &Self| {
+            side_key(board.current_player)
+                ^ castling_key(board.castling_rights)
+                ^ en_passant_key(board.en_passant)
+        };
+        self.zobrist_hash ^= state_key(self);
+        if piece == ChessPiece::King {
+            self.castling_rights.remove_all(color);
+        }
+        self.castling_rights.remove_for_square(&chess_move.from);
+        self.castling_rights.remove_for_square(&chess_move.to);
+        self.en_passant = (piece == ChessPiece::Pawn
+            && chess_move.from.y.abs_diff(chess_move.to.y) == 2)
+            .then(|| chess_move.from.forward(&color, 1))
+            .flatten();
+        if color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.current_player = !color;
+        self.zobrist_hash ^= state_key(self);
+    }
+
+    /// The piece `chess_move` takes and where it stands, see `ChessBoard::captured_piece`
+    pub fn captured_piece(&self, chess_move: &Move) -> Option<(ChessPiece, BoardPosition)> {
+        let Move { from, to, .. } = chess_move;
+        if let Some((piece, _)) = self.get_piece_at_position(to) {
+            return Some((piece, *to));
+        }
+        match self.get_piece_at_position(from) {
+            Some((ChessPiece::Pawn, _)) if self.en_passant == Some(*to) => {
+                let pawn = BoardPosition { x: to.x, y: from.y };
+                self.get_piece_at_position(&pawn)
+                    .map(|(piece, _)| (piece, pawn))
+            }
+            _ => None,
+        }
+    }
+
+    /// What the pawn turns into, if `chess_move` brings one to the last row
+    pub fn promotion_piece(&self, chess_move: &Move) -> Option<ChessPiece> {
+        match self.get_piece_at_position(&chess_move.from) {
+            Some((ChessPiece::Pawn, color))
+                if chess_move.to.is_in_bounds() && chess_move.to.forward(&color, 1).is_none() =>
+            {
+                Some(chess_move.promotion.unwrap_or(ChessPiece::Queen))
+            }
+            _ => None,
+        }
+    }
+
+    /// `None` while the game goes on, otherwise how it ended, like `ChessBoard::game_result`
+    pub fn game_result(&self) -> Option<GameResult> {
+        if self.get_legal_moves().is_empty() {
+            return if self.is_in_check(self.current_player) {
+                Some(GameResult::Checkmate {
+                    winner: !self.current_player,
+                })
+            } else {
+                Some(GameResult::Stalemate)
+            };
+        }
+        self.draw_by_rule()
+    }
+
+    pub fn draw_by_rule(&self) -> Option<GameResult> {
+        if self.halfmove_clock >= 100 {
+            Some(GameResult::FiftyMoveRule)
+        } else if self.has_insufficient_material() {
+            Some(GameResult::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// King against king, with at most one knight or any number of bishops on the same square color
+    pub fn has_insufficient_material(&self) -> bool {
+        let both = |piece| self.pieces(piece, Color::White) | self.pieces(piece, Color::Black);
+        if both(ChessPiece::Pawn) | both(ChessPiece::Rook) | both(ChessPiece::Queen) != 0 {
+            return false;
+        }
+        let knights = both(ChessPiece::Knight);
+        let bishops = both(ChessPiece::Bishoph);
+        (knights | bishops).count_ones() <= 1
+            || knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /// Counts the leaf nodes of the legal move tree `depth` half moves deep, see `ChessBoard::perft`
+    pub fn perft(&self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.get_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|chess_move| {
+                let mut moved_board = *self;
+                moved_board.apply_move_unchecked(chess_move);
+                moved_board.perft(depth - 1)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITIONS: [&str; 6] = [
+        ChessBoard::STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+
+    fn sorted_moves(moves: Vec<Move>) -> Vec<String> {
+        let mut moves: Vec<String> = moves.iter().map(Move::to_string).collect();
+        moves.sort();
+        moves
+    }
+
+    /// Walks the move tree of both boards side by side, everything has to agree in every position
+    fn assert_same_as_chess_board(board: &ChessBoard, depth: u8) {
+        let bit_board = BitBoard::from(board);
+        let fen = board.to_fen();
+        assert_eq!(bit_board.to_fen(), fen);
+        assert_eq!(bit_board.zobrist_hash(), board.zobrist_hash(), "{fen}");
+        assert_eq!(
+            bit_board.get_all_pieces_and_positions(),
+            board.get_all_pieces_and_positions()
+        );
+        assert_eq!(
+            sorted_moves(bit_board.get_legal_moves()),
+            sorted_moves(board.get_legal_moves()),
+            "{fen}"
+        );
+        assert_eq!(bit_board.game_result(), board.game_result(), "{fen}");
+        if depth == 0 {
+            return;
+        }
+        for chess_move in board.get_legal_moves() {
+            let mut moved_board = board.clone();
+            moved_board.play_move(&chess_move);
+            let mut moved_bit_board = bit_board;
+            assert!(moved_bit_board.play_move(&chess_move));
+            assert_eq!(
+                moved_bit_board,
+                BitBoard::from(&moved_board),
+                "{fen} {chess_move}"
+            );
+            assert_same_as_chess_board(&moved_board, depth - 1);
+        }
+    }
+
+    #[test]
+    fn agrees_with_chess_board() {
+        for fen in POSITIONS {
+            assert_same_as_chess_board(&ChessBoard::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn converts_losslessly() {
+        for fen in POSITIONS {
+            let board = ChessBoard::from_fen(fen).unwrap();
+            let bit_board = BitBoard::from(&board);
+            let back = ChessBoard::from(&bit_board);
+            assert_eq!(back.to_fen(), fen);
+            assert_eq!(back.fields, board.fields);
+            assert_eq!(back.zobrist_hash(), board.zobrist_hash());
+            assert_eq!(BitBoard::from(&back), bit_board);
+        }
+    }
+
+    #[test]
+    fn attack_tables() {
+        let square = |name: &str| square_of(&name.parse().unwrap());
+        let bits = |names: &[&str]| names.iter().fold(0, |bits, name| bits | 1 << square(name));
+        assert_eq!(KNIGHT_ATTACKS[square("a1")], bits(&["b3", "c2"]));
+        assert_eq!(KING_ATTACKS[square("h8")], bits(&["g8", "g7", "h7"]));
+        assert_eq!(PAWN_ATTACKS[0][square("e4")], bits(&["d5", "f5"]));
+        assert_eq!(PAWN_ATTACKS[1][square("a5")], bits(&["b4"]));
+        // the rook stops at the first piece, whatever its color
+        let occupied = bits(&["d6", "f4", "d2"]);
+        assert_eq!(
+            slider_attacks(square("d4"), occupied, &ROOK_DIRECTIONS),
+            bits(&["d5", "d6", "e4", "f4", "d3", "d2", "c4", "b4", "a4"])
+        );
+        assert_eq!(LIGHT_SQUARES & bits(&["a1", "h1"]), bits(&["h1"]));
+    }
+
+    #[test]
+    fn perft_matches() {
+        for (fen, expected) in [
+            (ChessBoard::STARTING_FEN, 197281),
+            (POSITIONS[1], 97862),
+            (POSITIONS[2], 43238),
+            (POSITIONS[3], 9467),
+            (POSITIONS[4], 62379),
+        ] {
+            let depth = if fen == POSITIONS[2] || fen == ChessBoard::STARTING_FEN {
+                4
+            } else {
+                3
+            };
+            assert_eq!(
+                BitBoard::from_fen(fen).unwrap().perft(depth),
+                expected,
+                "{fen}"
+            );
+        }
+    }
+}
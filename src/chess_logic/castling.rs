@@ -0,0 +1,81 @@
+use super::{BoardPosition, Color};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        Self {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+    pub fn none() -> Self {
+        Self {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    pub fn kingside(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_kingside,
+            Color::Black => self.black_kingside,
+        }
+    }
+    pub fn queenside(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queenside,
+            Color::Black => self.black_queenside,
+        }
+    }
+
+    /// The king moved, so this color can't castle on either side anymore
+    pub fn remove_all(&mut self, color: Color) {
+        match color {
+            Color::White => {
+                self.white_kingside = false;
+                self.white_queenside = false;
+            }
+            Color::Black => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+        }
+    }
+
+    /// Something left or arrived on `position`. If it is one of the rook corners,
+    /// the corresponding right is gone (the rook either moved or got captured)
+    pub fn remove_for_square(&mut self, position: &BoardPosition) {
+        match (position.x, position.y) {
+            (0, 0) => self.black_queenside = false,
+            (7, 0) => self.black_kingside = false,
+            (0, 7) => self.white_queenside = false,
+            (7, 7) => self.white_kingside = false,
+            _ => {}
+        }
+    }
+
+    /// The row the king and rooks start on
+    pub fn home_row(color: Color) -> u8 {
+        match color {
+            Color::White => 7,
+            Color::Black => 0,
+        }
+    }
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        Self::all()
+    }
+}
@@ -0,0 +1,104 @@
+use super::{BoardPosition, ChessPiece};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: BoardPosition,
+    pub to: BoardPosition,
+    /// What a pawn reaching the last row turns into. `None` on a promoting move means queen.
+    pub promotion: Option<ChessPiece>,
+}
+
+impl Move {
+    pub fn new(from: BoardPosition, to: BoardPosition) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+    pub fn promoting(from: BoardPosition, to: BoardPosition, piece: ChessPiece) -> Self {
+        Self {
+            from,
+            to,
+            promotion: Some(piece),
+        }
+    }
+}
+
+/// Writes the move in the long algebraic notation UCI uses, like `e2e4` or `e7e8q`.
+/// Castling is written as the king's move, `e1g1`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(piece) = self.promotion {
+            write!(f, "{}", piece.fen_symbol())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMoveError(pub String);
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a move in UCI notation", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
+/// Reads a move in UCI notation. This only checks the format, not if the move is legal.
+impl FromStr for Move {
+    type Err = ParseMoveError;
+    fn from_str(uci: &str) -> Result<Self, Self::Err> {
+        let error = || ParseMoveError(uci.to_string());
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(error());
+        }
+        let from = uci[0..2].parse().map_err(|_| error())?;
+        let to = uci[2..4].parse().map_err(|_| error())?;
+        let promotion = match uci[4..].chars().next() {
+            None => None,
+            Some(symbol) => Some(
+                ChessPiece::from_fen_symbol(symbol)
+                    .filter(|piece| ChessPiece::PROMOTION_CHOICES.contains(piece))
+                    .ok_or_else(error)?,
+            ),
+        };
+        Ok(Self {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uci_round_trips() {
+        for uci in ["e2e4", "e1g1", "e7e8q", "a2a1n"] {
+            let chess_move: Move = uci.parse().unwrap();
+            assert_eq!(chess_move.to_string(), uci);
+        }
+        assert_eq!(
+            "e7e8r".parse(),
+            Ok(Move::promoting(
+                BoardPosition { x: 4, y: 1 },
+                BoardPosition { x: 4, y: 0 },
+                ChessPiece::Rook
+            ))
+        );
+        for invalid in ["", "e2e", "e2e9", "e7e8k", "e7e8qq", "0000"] {
+            assert_eq!(
+                invalid.parse::<Move>(),
+                Err(ParseMoveError(invalid.to_string()))
+            );
+        }
+    }
+}
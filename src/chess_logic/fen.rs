@@ -0,0 +1,331 @@
+use super::{BoardPosition, CastlingRights, ChessBoard, ChessPiece, Color};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// What is wrong with a FEN string, by field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN has 6 fields separated by spaces, or 4 if the move counters are left out
+    FieldCount(usize),
+    /// The piece placement needs 8 rows separated by '/'
+    RowCount(usize),
+    /// A row of the piece placement doesn't cover exactly 8 squares. Rows count from the top, starting at 0.
+    RowLength {
+        row: usize,
+        length: usize,
+    },
+    UnknownPiece(char),
+    SideToMove(String),
+    CastlingRights(String),
+    EnPassant(String),
+    HalfmoveClock(String),
+    FullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FieldCount(count) => write!(f, "expected 4 or 6 fields, found {count}"),
+            Self::RowCount(count) => {
+                write!(f, "piece placement: expected 8 rows, found {count}")
+            }
+            Self::RowLength { row, length } => write!(
+                f,
+                "piece placement: row {} covers {length} squares instead of 8",
+                row + 1
+            ),
+            Self::UnknownPiece(symbol) => write!(f, "piece placement: unknown piece '{symbol}'"),
+            Self::SideToMove(field) => {
+                write!(f, "side to move: expected 'w' or 'b', found '{field}'")
+            }
+            Self::CastlingRights(field) => write!(f, "castling rights: invalid '{field}'"),
+            Self::EnPassant(field) => write!(f, "en passant square: invalid '{field}'"),
+            Self::HalfmoveClock(field) => write!(f, "halfmove clock: invalid '{field}'"),
+            Self::FullmoveNumber(field) => write!(f, "fullmove number: invalid '{field}'"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Boards are saved as their FEN, which holds everything about the position
+impl Serialize for ChessBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for ChessBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        ChessBoard::from_fen(&fen).map_err(de::Error::custom)
+    }
+}
+
+impl ChessBoard {
+    pub const STARTING_FEN: &'static str =
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+        let mut board = ChessBoard::new();
+
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 8 {
+            return Err(FenError::RowCount(rows.len()));
+        }
+        for (y, row) in rows.iter().enumerate() {
+            let mut x = 0;
+            for symbol in row.chars() {
+                if let Some(empty) = symbol.to_digit(10).filter(|empty| (1..=8).contains(empty)) {
+                    x += empty as usize;
+                    continue;
+                }
+                let piece = ChessPiece::from_fen_symbol(symbol.to_ascii_lowercase())
+                    .ok_or(FenError::UnknownPiece(symbol))?;
+                let color = if symbol.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                if x < 8 {
+                    board.fields[[y, x]] = Some((piece, color));
+                }
+                x += 1;
+            }
+            if x != 8 {
+                return Err(FenError::RowLength { row: y, length: x });
+            }
+        }
+
+        board.current_player = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(FenError::SideToMove(other.to_string())),
+        };
+
+        board.castling_rights = CastlingRights::none();
+        if fields[2] != "-" {
+            for symbol in fields[2].chars() {
+                let right = match symbol {
+                    'K' => &mut board.castling_rights.white_kingside,
+                    'Q' => &mut board.castling_rights.white_queenside,
+                    'k' => &mut board.castling_rights.black_kingside,
+                    'q' => &mut board.castling_rights.black_queenside,
+                    _ => return Err(FenError::CastlingRights(fields[2].to_string())),
+                };
+                if *right {
+                    // every right may only be listed once
+                    return Err(FenError::CastlingRights(fields[2].to_string()));
+                }
+                *right = true;
+            }
+        }
+
+        board.en_passant = match fields[3] {
+            "-" => None,
+            square => {
+                // the skipped square is right behind the pawn that just moved
+                let skipped_row = match board.current_player {
+                    Color::White => 2,
+                    Color::Black => 5,
+                };
+                Some(
+                    square
+                        .parse::<BoardPosition>()
+                        .ok()
+                        .filter(|position| position.y == skipped_row)
+                        .ok_or(FenError::EnPassant(square.to_string()))?,
+                )
+            }
+        };
+
+        if fields.len() == 6 {
+            board.halfmove_clock = fields[4]
+                .parse()
+                .map_err(|_| FenError::HalfmoveClock(fields[4].to_string()))?;
+            board.fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or(FenError::FullmoveNumber(fields[5].to_string()))?;
+        }
+
+        board.refresh_zobrist_hash();
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let placement = self
+            .fields
+            .rows()
+            .into_iter()
+            .map(|row| {
+                let mut out = String::new();
+                let mut empty = 0;
+                for field in row {
+                    match field {
+                        Some((piece, color)) => {
+                            if empty > 0 {
+                                out.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            let symbol = piece.fen_symbol();
+                            out.push(match color {
+                                Color::White => symbol.to_ascii_uppercase(),
+                                Color::Black => symbol,
+                            });
+                        }
+                        None => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    out.push_str(&empty.to_string());
+                }
+                out
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let side_to_move = match self.current_player {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let rights = self.castling_rights;
+        let mut castling: String = [
+            (rights.white_kingside, 'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside, 'k'),
+            (rights.black_queenside, 'q'),
+        ]
+        .iter()
+        .filter_map(|(allowed, symbol)| allowed.then_some(symbol))
+        .collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = self
+            .en_passant
+            .map(|position| position.to_string())
+            .unwrap_or("-".to_string());
+
+        format!(
+            "{placement} {side_to_move} {castling} {en_passant} {} {}",
+            self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
+
+impl ChessPiece {
+    /// The lowercase letter FEN uses for the piece
+    pub fn fen_symbol(&self) -> char {
+        match self {
+            ChessPiece::Pawn => 'p',
+            ChessPiece::Knight => 'n',
+            ChessPiece::Bishoph => 'b',
+            ChessPiece::Rook => 'r',
+            ChessPiece::Queen => 'q',
+            ChessPiece::King => 'k',
+        }
+    }
+    pub fn from_fen_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            'p' => Some(ChessPiece::Pawn),
+            'n' => Some(ChessPiece::Knight),
+            'b' => Some(ChessPiece::Bishoph),
+            'r' => Some(ChessPiece::Rook),
+            'q' => Some(ChessPiece::Queen),
+            'k' => Some(ChessPiece::King),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_round_trips() {
+        let board = ChessBoard::from_fen(ChessBoard::STARTING_FEN).unwrap();
+        assert_eq!(board.fields, ChessBoard::init_default().fields);
+        assert_eq!(board.castling_rights, CastlingRights::all());
+        assert_eq!(
+            ChessBoard::init_default().to_fen(),
+            ChessBoard::STARTING_FEN
+        );
+    }
+
+    #[test]
+    fn fen_tracks_moves() {
+        let mut board = ChessBoard::init_default();
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 6 }, &BoardPosition { x: 4, y: 4 }));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        assert!(board.move_piece(&BoardPosition { x: 6, y: 0 }, &BoardPosition { x: 5, y: 2 }));
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 7 }, &BoardPosition { x: 4, y: 6 }));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+        );
+    }
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            assert_eq!(ChessBoard::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_errors_name_the_field() {
+        let cases = [
+            ("8/8/8/8/8/8/8/8 w - -  0", FenError::FieldCount(5)),
+            ("8/8/8/8/8/8/8 w - - 0 1", FenError::RowCount(7)),
+            (
+                "8/8/8/8/8/8/8/7 w - - 0 1",
+                FenError::RowLength { row: 7, length: 7 },
+            ),
+            (
+                "8/8/8/ppppppppp/8/8/8/8 w - - 0 1",
+                FenError::RowLength { row: 3, length: 9 },
+            ),
+            ("8/8/8/8/8/8/8/7x w - - 0 1", FenError::UnknownPiece('x')),
+            (
+                "8/8/8/8/8/8/8/8 x - - 0 1",
+                FenError::SideToMove("x".to_string()),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w KK - 0 1",
+                FenError::CastlingRights("KK".to_string()),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w - e4 0 1",
+                FenError::EnPassant("e4".to_string()),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w - - x 1",
+                FenError::HalfmoveClock("x".to_string()),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w - - 0 0",
+                FenError::FullmoveNumber("0".to_string()),
+            ),
+        ];
+        for (fen, error) in cases {
+            assert_eq!(ChessBoard::from_fen(fen).err(), Some(error), "{fen}");
+        }
+    }
+}
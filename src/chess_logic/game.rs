@@ -0,0 +1,93 @@
+use super::{ChessBoard, GameResult, Move};
+
+/// A board together with everything that happened on it, needed to spot repetitions
+#[derive(Clone)]
+pub struct Game {
+    initial_board: ChessBoard,
+    board: ChessBoard,
+    moves: Vec<Move>,
+    /// `zobrist_hash` of every position so far, including the current one
+    history: Vec<u64>,
+}
+
+impl Game {
+    pub fn new(board: ChessBoard) -> Self {
+        let history = vec![board.zobrist_hash()];
+        Self {
+            initial_board: board.clone(),
+            board,
+            moves: Vec::new(),
+            history,
+        }
+    }
+
+    /// The board before the first move
+    pub fn initial_board(&self) -> &ChessBoard {
+        &self.initial_board
+    }
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn play_move(&mut self, chess_move: &Move) -> bool {
+        if !self.board.play_move(chess_move) {
+            return false;
+        }
+        self.moves.push(*chess_move);
+        self.history.push(self.board.zobrist_hash());
+        true
+    }
+
+    /// How often the current position occurred, counting itself
+    pub fn repetitions(&self) -> usize {
+        let current = self.board.zobrist_hash();
+        // positions before the last capture or pawn move can't come back
+        self.history
+            .iter()
+            .rev()
+            .take(self.board.halfmove_clock as usize + 1)
+            .filter(|&&key| key == current)
+            .count()
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.board
+            .game_result()
+            .or_else(|| (self.repetitions() >= 3).then_some(GameResult::ThreefoldRepetition))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardPosition;
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = Game::new(ChessBoard::init_default());
+        let knight_moves = [
+            ((6, 7), (5, 5)),
+            ((6, 0), (5, 2)),
+            ((5, 5), (6, 7)),
+            ((5, 2), (6, 0)),
+        ];
+        for _ in 0..2 {
+            assert_eq!(game.result(), None);
+            for (from, to) in knight_moves {
+                assert!(game.play_move(&Move::new(
+                    BoardPosition {
+                        x: from.0,
+                        y: from.1
+                    },
+                    BoardPosition { x: to.0, y: to.1 }
+                )));
+            }
+        }
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.result(), Some(GameResult::ThreefoldRepetition));
+        assert_eq!(game.moves().len(), 8);
+    }
+}
@@ -1,11 +1,75 @@
+mod bitboard;
+mod castling;
+mod chess_move;
+mod fen;
+mod game;
+mod perft;
+mod pgn;
 mod pieces;
+mod san;
+mod undo;
+mod zobrist;
 
+pub use bitboard::BitBoard;
+pub use castling::CastlingRights;
+pub use chess_move::{Move, ParseMoveError};
+pub use fen::FenError;
+pub use game::Game;
 use ndarray::Array2;
-pub use pieces::{BoardPosition, ChessPiece, Color, Piece};
+pub use pgn::{PgnError, PgnErrorKind, PgnGame, PgnMove, PgnResult, SEVEN_TAG_ROSTER};
+pub use pieces::{BoardPosition, ChessPiece, Color, ParseSquareError, Piece};
+pub use san::SanError;
+pub use undo::Undo;
 
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const STRAIGHT_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const DIAGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    Checkmate {
+        winner: Color,
+    },
+    Stalemate,
+    /// A hundred half moves without a pawn move or a capture
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    /// Nobody has enough pieces left to ever checkmate
+    InsufficientMaterial,
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            Self::Checkmate { winner } => Some(*winner),
+            _ => None,
+        }
+    }
+}
+
+/// The position and the state of the game in it. Moves keep `zobrist_hash` up to date,
+/// after changing the fields directly it needs `refresh_zobrist_hash`.
 #[derive(Clone)]
 pub struct ChessBoard {
     pub fields: Array2<Option<(ChessPiece, Color)>>,
+    pub current_player: Color,
+    pub castling_rights: CastlingRights,
+    /// The square a pawn skipped with a double push in the last move, where it can be captured en passant
+    pub en_passant: Option<BoardPosition>,
+    /// Half moves since the last pawn move or capture, for the fifty-move rule
+    pub halfmove_clock: u32,
+    /// Starts at 1 and goes up after every move of black
+    pub fullmove_number: u32,
+    zobrist_hash: u64,
 }
 
 impl ChessBoard {
@@ -23,6 +87,13 @@ impl ChessBoard {
         let empty_field: Option<(ChessPiece, Color)> = None;
         ChessBoard {
             fields: Array2::from_elem([8, 8], empty_field),
+            current_player: Color::White,
+            castling_rights: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            // an empty board with white to move and no rights hashes to 0
+            zobrist_hash: 0,
         }
     }
     pub fn init_default() -> Self {
@@ -45,39 +116,577 @@ impl ChessBoard {
                     *on_board = on_board.or(*in_default_layout);
                 });
         }
+        board.castling_rights = CastlingRights::all();
+        board.refresh_zobrist_hash();
         board
     }
+
+    /// Checks if any piece of color `by` could capture on `position`.
+    /// Unlike `get_moves` this doesn't care whose turn it is or whether `position` is occupied.
+    pub fn is_attacked(&self, position: &BoardPosition, by: Color) -> bool {
+        let is_attacker = |pos: Option<BoardPosition>, pieces: &[ChessPiece]| {
+            pos.and_then(|pos| self.get_piece_at_position(&pos))
+                .is_some_and(|(piece, color)| color == by && pieces.contains(&piece))
+        };
+
+        // pawns capture diagonally forward, so we look diagonally backward from their point of view
+        if is_attacker(position.diag_bl(&by, 1), &[ChessPiece::Pawn])
+            || is_attacker(position.diag_br(&by, 1), &[ChessPiece::Pawn])
+        {
+            return true;
+        }
+        if KNIGHT_JUMPS
+            .iter()
+            .any(|&(dx, dy)| is_attacker(position.offset(dx, dy), &[ChessPiece::Knight]))
+        {
+            return true;
+        }
+        if STRAIGHT_DIRECTIONS
+            .iter()
+            .chain(DIAGONAL_DIRECTIONS.iter())
+            .any(|&(dx, dy)| is_attacker(position.offset(dx, dy), &[ChessPiece::King]))
+        {
+            return true;
+        }
+
+        // sliding pieces: the first piece we hit in each direction decides
+        let sliders = [
+            (STRAIGHT_DIRECTIONS, ChessPiece::Rook),
+            (DIAGONAL_DIRECTIONS, ChessPiece::Bishoph),
+        ];
+        sliders.iter().any(|(directions, slider)| {
+            directions.iter().any(|&(dx, dy)| {
+                let mut last_position = *position;
+                while let Some(next_position) = last_position.offset(dx, dy) {
+                    if let Some((piece, color)) = self.get_piece_at_position(&next_position) {
+                        return color == by && (piece == *slider || piece == ChessPiece::Queen);
+                    }
+                    last_position = next_position;
+                }
+                false
+            })
+        })
+    }
     fn force_move_piece(&mut self, from: &BoardPosition, to: &BoardPosition) {
         if !to.is_in_bounds() {
             panic!("accessing field out of bounds")
         }
 
-        self.fields[to.get_idx()] = self.fields[from.get_idx()].take();
+        let piece = self.fields[from.get_idx()];
+        self.set_field(from, None);
+        self.set_field(to, piece);
     }
 
     pub fn move_piece(&mut self, from: &BoardPosition, to: &BoardPosition) -> bool {
+        self.play_move(&Move::new(*from, *to))
+    }
+
+    /// Checks if moving from `from` to `to` is allowed and brings a pawn to the last row
+    pub fn is_promotion(&self, from: &BoardPosition, to: &BoardPosition) -> bool {
+        self.reaches_last_row(from, to) && self.is_legal(&Move::new(*from, *to))
+    }
+
+    /// The piece `chess_move` takes and where it stands, which is not where the move ends for en passant
+    pub fn captured_piece(&self, chess_move: &Move) -> Option<(ChessPiece, BoardPosition)> {
+        let Move { from, to, .. } = chess_move;
+        if let Some((piece, _)) = self.get_piece_at_position(to) {
+            return Some((piece, *to));
+        }
+        match self.get_piece_at_position(from) {
+            Some((ChessPiece::Pawn, _)) if self.en_passant == Some(*to) => {
+                Some((ChessPiece::Pawn, BoardPosition { x: to.x, y: from.y }))
+            }
+            _ => None,
+        }
+    }
+
+    /// What the pawn turns into, if `chess_move` brings one to the last row
+    pub fn promotion_piece(&self, chess_move: &Move) -> Option<ChessPiece> {
+        self.reaches_last_row(&chess_move.from, &chess_move.to)
+            .then(|| chess_move.promotion.unwrap_or(ChessPiece::Queen))
+    }
+
+    fn reaches_last_row(&self, from: &BoardPosition, to: &BoardPosition) -> bool {
+        self.get_piece_at_position(from)
+            .is_some_and(|(piece, color)| {
+                piece == ChessPiece::Pawn && to.is_in_bounds() && to.forward(&color, 1).is_none()
+            })
+    }
+
+    /// Plays `chess_move` if it is allowed. A pawn reaching the last row without a
+    /// promotion piece turns into a queen.
+    pub fn play_move(&mut self, chess_move: &Move) -> bool {
+        match self.after_move(chess_move) {
+            Some(moved_board) => {
+                *self = moved_board;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_legal(&self, chess_move: &Move) -> bool {
+        self.after_move(chess_move).is_some()
+    }
+
+    /// The board after `chess_move`, or `None` if the move isn't allowed
+    fn after_move(&self, chess_move: &Move) -> Option<ChessBoard> {
+        let Move {
+            from,
+            to,
+            promotion,
+        } = chess_move;
+
         // check if it is in bound
-        if !to.is_in_bounds() {
-            return false;
+        if !to.is_in_bounds() || !from.is_in_bounds() {
+            return None;
         }
 
+        // it has to be our turn
+        let (_, color) = self
+            .get_piece_at_position(from)
+            .filter(|(_, color)| *color == self.current_player)?;
+
         // colors can't be the same
         if self
             .get_piece_at_position(to)
-            .zip(self.get_piece_at_position(from))
-            .is_some_and(|((_, color_to), (_, color_from))| color_to == color_from)
+            .is_some_and(|(_, color_to)| color_to == color)
         {
-            return false;
+            return None;
         }
 
         // move has to be in the allowed set
-        if ChessPiece::get_moves(from, self)[to.get_idx()] != true {
-            return false;
+        if !ChessPiece::get_moves(from, self)[to.get_idx()] {
+            return None;
+        }
+
+        // only pawns reaching the last row promote, and never to a pawn or king
+        let is_promotion = self.reaches_last_row(from, to);
+        if promotion
+            .is_some_and(|piece| !is_promotion || !ChessPiece::PROMOTION_CHOICES.contains(&piece))
+        {
+            return None;
+        }
+
+        // we can't leave our own king in check
+        let mut moved_board = self.clone();
+        moved_board.apply_move_unchecked(chess_move);
+        (!moved_board.is_in_check(color)).then_some(moved_board)
+    }
+
+    /// Moves the piece and takes care of everything that comes with it (castling, en passant,
+    /// promotion, whose turn it is), without checking if the move is allowed.
+    fn apply_move_unchecked(&mut self, chess_move: &Move) {
+        let Move {
+            from,
+            to,
+            promotion,
+        } = chess_move;
+        let is_promotion = self.reaches_last_row(from, to);
+
+        let moving_piece = self.fields[from.get_idx()];
+        let is_capture = self.fields[to.get_idx()].is_some();
+        let is_pawn_move = moving_piece.is_some_and(|(piece, _)| piece == ChessPiece::Pawn);
+        if is_capture || is_pawn_move {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
         }
+        self.force_move_piece(from, to);
+
+        if let Some((ChessPiece::King, _)) = moving_piece {
+            // a king moving two squares is castling, the rook jumps over it
+            if from.x.abs_diff(to.x) == 2 {
+                let (rook_from, rook_to) = if to.x > from.x { (7, 5) } else { (0, 3) };
+                self.force_move_piece(
+                    &BoardPosition {
+                        x: rook_from,
+                        y: from.y,
+                    },
+                    &BoardPosition {
+                        x: rook_to,
+                        y: from.y,
+                    },
+                );
+            }
+        }
+
+        if let Some((ChessPiece::Pawn, color)) = moving_piece {
+            if self.en_passant == Some(*to) {
+                // the captured pawn is not on the square we moved to, but just behind it
+                self.set_field(&BoardPosition { x: to.x, y: from.y }, None);
+            }
+            if is_promotion {
+                self.set_field(to, Some((promotion.unwrap_or(ChessPiece::Queen), color)));
+            }
+        }
+
+        self.update_state(|board| {
+            if let Some((ChessPiece::King, color)) = moving_piece {
+                board.castling_rights.remove_all(color);
+            }
+            // a rook leaving its corner or getting captured there loses the right
+            board.castling_rights.remove_for_square(from);
+            board.castling_rights.remove_for_square(to);
+
+            board.en_passant = match moving_piece {
+                Some((ChessPiece::Pawn, color)) if from.y.abs_diff(to.y) == 2 => {
+                    from.forward(&color, 1)
+                }
+                _ => None,
+            };
+
+            if board.current_player == Color::Black {
+                board.fullmove_number += 1;
+            }
+            board.current_player = !board.current_player;
+        });
+    }
+
+    pub fn get_king_position(&self, color: Color) -> Option<BoardPosition> {
+        self.get_all_pieces_and_positions()
+            .into_iter()
+            .find(|(piece, piece_color, _)| *piece == ChessPiece::King && *piece_color == color)
+            .map(|(_, _, position)| position)
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        self.get_king_position(color)
+            .is_some_and(|king| self.is_attacked(&king, !color))
+    }
+
+    /// All moves the current player is allowed to make. Promotions are listed once per piece.
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        self.clone().generate_legal_moves()
+    }
+
+    /// Like `get_legal_moves`, but tries every move on this board and takes it back again
+    /// instead of copying the board for each one. The board is unchanged afterwards.
+    pub fn generate_legal_moves(&mut self) -> Vec<Move> {
+        let color = self.current_player;
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|chess_move| {
+            let undo = self.make_move(chess_move);
+            let legal = !self.is_in_check(color);
+            self.unmake_move(undo);
+            legal
+        });
+        moves
+    }
+
+    /// The moves of the current player's pieces, including those that leave the own king in check
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        self.get_all_pieces_and_positions()
+            .into_iter()
+            .filter(|(_, color, _)| *color == self.current_player)
+            .flat_map(|(_, _, from)| {
+                ChessPiece::get_moves(&from, self)
+                    .indexed_iter()
+                    .filter_map(|((row, col), can_move)| {
+                        can_move.then_some(BoardPosition::from_idx(row, col))
+                    })
+                    .flat_map(|to| {
+                        if self.reaches_last_row(&from, &to) {
+                            ChessPiece::PROMOTION_CHOICES
+                                .iter()
+                                .map(|&piece| Move::promoting(from, to, piece))
+                                .collect()
+                        } else {
+                            vec![Move::new(from, to)]
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// `None` while the game goes on, otherwise how it ended. Repetitions need the
+    /// history of the game, see `Game::result` for that.
+    pub fn game_result(&self) -> Option<GameResult> {
+        if self.get_legal_moves().is_empty() {
+            return if self.is_in_check(self.current_player) {
+                Some(GameResult::Checkmate {
+                    winner: !self.current_player,
+                })
+            } else {
+                Some(GameResult::Stalemate)
+            };
+        }
+        self.draw_by_rule()
+    }
+
+    /// The draws that don't need to look at the legal moves, cheap enough to check while searching
+    pub fn draw_by_rule(&self) -> Option<GameResult> {
+        if self.halfmove_clock >= 100 {
+            Some(GameResult::FiftyMoveRule)
+        } else if self.has_insufficient_material() {
+            Some(GameResult::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// King against king, with at most one knight or any number of bishops on the same square color
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut minors = Vec::new();
+        for (piece, _, position) in self.get_all_pieces_and_positions() {
+            match piece {
+                ChessPiece::King => {}
+                ChessPiece::Knight | ChessPiece::Bishoph => minors.push((piece, position)),
+                ChessPiece::Pawn | ChessPiece::Rook | ChessPiece::Queen => return false,
+            }
+        }
+        let square_color = |position: &BoardPosition| (position.x + position.y) % 2;
+        match minors.as_slice() {
+            [] | [_] => true,
+            [(_, first), ..] => minors.iter().all(|(piece, position)| {
+                *piece == ChessPiece::Bishoph && square_color(position) == square_color(first)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ChessPiece::*;
+    use Color::*;
+
+    fn clear(board: &mut ChessBoard, positions: &[(u8, u8)]) {
+        for &(x, y) in positions {
+            board.fields[BoardPosition { x, y }.get_idx()] = None;
+        }
+    }
+
+    #[test]
+    fn castling_moves_rook() {
+        let mut board = ChessBoard::init_default();
+        clear(&mut board, &[(5, 7), (6, 7), (1, 0), (2, 0), (3, 0)]);
+
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 7 }, &BoardPosition { x: 6, y: 7 }));
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 5, y: 7 }),
+            Some((Rook, White))
+        );
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 7, y: 7 }),
+            None
+        );
+        assert!(!board.castling_rights.kingside(White));
+        assert!(!board.castling_rights.queenside(White));
+
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 0 }, &BoardPosition { x: 2, y: 0 }));
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 3, y: 0 }),
+            Some((Rook, Black))
+        );
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 0, y: 0 }),
+            None
+        );
+    }
+
+    #[test]
+    fn castling_rights_are_lost() {
+        let mut board = ChessBoard::init_default();
+        clear(&mut board, &[(5, 7), (6, 7), (1, 7), (2, 7), (3, 7)]);
+
+        // rook goes away and comes back
+        assert!(board.move_piece(&BoardPosition { x: 7, y: 7 }, &BoardPosition { x: 6, y: 7 }));
+        board.current_player = White;
+        assert!(board.move_piece(&BoardPosition { x: 6, y: 7 }, &BoardPosition { x: 7, y: 7 }));
+        assert!(!board.castling_rights.kingside(White));
+        assert!(board.castling_rights.queenside(White));
+        assert!(!board.move_piece(&BoardPosition { x: 4, y: 7 }, &BoardPosition { x: 6, y: 7 }));
+
+        // the queenside rook gets captured
+        board.current_player = Black;
+        board.fields[BoardPosition { x: 2, y: 6 }.get_idx()] = Some((Knight, Black));
+        assert!(board.move_piece(&BoardPosition { x: 2, y: 6 }, &BoardPosition { x: 0, y: 7 }));
+        assert!(!board.castling_rights.queenside(White));
+    }
+
+    #[test]
+    fn no_castling_through_or_out_of_check() {
+        let mut board = ChessBoard::init_default();
+        clear(&mut board, &[(5, 7), (6, 7), (5, 6)]);
+        let king = BoardPosition { x: 4, y: 7 };
+        let castled = BoardPosition { x: 6, y: 7 };
+
+        // a rook on f4 attacks f1, which the king would pass
+        board.fields[BoardPosition { x: 5, y: 4 }.get_idx()] = Some((Rook, Black));
+        assert!(!ChessPiece::get_moves(&king, &board)[castled.get_idx()]);
+
+        // a bishop on b4 gives check
+        board.fields[BoardPosition { x: 5, y: 4 }.get_idx()] = None;
+        clear(&mut board, &[(3, 6)]);
+        board.fields[BoardPosition { x: 1, y: 4 }.get_idx()] = Some((Bishoph, Black));
+        assert!(board.is_attacked(&king, Black));
+        assert!(!ChessPiece::get_moves(&king, &board)[castled.get_idx()]);
+
+        board.fields[BoardPosition { x: 1, y: 4 }.get_idx()] = None;
+        assert!(ChessPiece::get_moves(&king, &board)[castled.get_idx()]);
+    }
+
+    #[test]
+    fn en_passant_captures() {
+        let mut board = ChessBoard::init_default();
+        board.fields[BoardPosition { x: 4, y: 3 }.get_idx()] = Some((Pawn, White));
+        board.current_player = Black;
+
+        assert!(board.move_piece(&BoardPosition { x: 3, y: 1 }, &BoardPosition { x: 3, y: 3 }));
+        assert_eq!(board.en_passant, Some(BoardPosition { x: 3, y: 2 }));
+
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 3 }, &BoardPosition { x: 3, y: 2 }));
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 3, y: 2 }),
+            Some((Pawn, White))
+        );
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 3, y: 3 }),
+            None
+        );
+        assert_eq!(board.en_passant, None);
+    }
+
+    #[test]
+    fn en_passant_expires() {
+        let mut board = ChessBoard::init_default();
+        board.fields[BoardPosition { x: 4, y: 3 }.get_idx()] = Some((Pawn, White));
+        board.current_player = Black;
+
+        assert!(board.move_piece(&BoardPosition { x: 3, y: 1 }, &BoardPosition { x: 3, y: 3 }));
+        assert!(board.move_piece(&BoardPosition { x: 0, y: 6 }, &BoardPosition { x: 0, y: 5 }));
+        assert!(board.move_piece(&BoardPosition { x: 0, y: 1 }, &BoardPosition { x: 0, y: 2 }));
+        assert!(!board.move_piece(&BoardPosition { x: 4, y: 3 }, &BoardPosition { x: 3, y: 2 }));
+    }
+
+    #[test]
+    fn pawns_promote() {
+        let mut board = ChessBoard::new();
+        board.fields[BoardPosition { x: 0, y: 1 }.get_idx()] = Some((Pawn, White));
+        board.fields[BoardPosition { x: 7, y: 6 }.get_idx()] = Some((Pawn, Black));
+
+        let from = BoardPosition { x: 0, y: 1 };
+        let to = BoardPosition { x: 0, y: 0 };
+        assert!(board.is_promotion(&from, &to));
+        assert!(!board.play_move(&Move::promoting(from, to, King)));
+        assert!(board.play_move(&Move::promoting(from, to, Knight)));
+        assert_eq!(board.get_piece_at_position(&to), Some((Knight, White)));
+
+        // no promotion piece means queen
+        assert!(board.move_piece(&BoardPosition { x: 7, y: 6 }, &BoardPosition { x: 7, y: 7 }));
+        assert_eq!(
+            board.get_piece_at_position(&BoardPosition { x: 7, y: 7 }),
+            Some((Queen, Black))
+        );
+    }
+
+    #[test]
+    fn only_pawns_on_the_last_row_promote() {
+        let mut board = ChessBoard::init_default();
+        let from = BoardPosition { x: 0, y: 6 };
+        let to = BoardPosition { x: 0, y: 5 };
+        assert!(!board.is_promotion(&from, &to));
+        assert!(!board.play_move(&Move::promoting(from, to, Queen)));
+        assert!(board.play_move(&Move::new(from, to)));
+    }
+
+    #[test]
+    fn cannot_leave_king_in_check() {
+        let mut board = ChessBoard::init_default();
+        // a knight on e7 is pinned by a rook on e4
+        board.fields[BoardPosition { x: 4, y: 1 }.get_idx()] = Some((Knight, Black));
+        board.fields[BoardPosition { x: 4, y: 4 }.get_idx()] = Some((Rook, White));
+        board.current_player = Black;
+        assert!(!board.is_in_check(Black));
+        assert!(!board.move_piece(&BoardPosition { x: 4, y: 1 }, &BoardPosition { x: 2, y: 2 }));
+
+        // without the knight it's check, and we have to do something about it
+        clear(&mut board, &[(4, 1)]);
+        assert!(board.is_in_check(Black));
+        assert!(!board.move_piece(&BoardPosition { x: 0, y: 1 }, &BoardPosition { x: 0, y: 2 }));
+        assert!(board.move_piece(&BoardPosition { x: 3, y: 0 }, &BoardPosition { x: 4, y: 1 }));
+    }
+
+    #[test]
+    fn fools_mate() {
+        let mut board = ChessBoard::init_default();
+        for (from, to) in [
+            ((5, 6), (5, 5)),
+            ((4, 1), (4, 3)),
+            ((6, 6), (6, 4)),
+            ((3, 0), (7, 4)),
+        ] {
+            assert_eq!(board.game_result(), None);
+            assert!(board.move_piece(
+                &BoardPosition {
+                    x: from.0,
+                    y: from.1
+                },
+                &BoardPosition { x: to.0, y: to.1 }
+            ));
+        }
+        assert!(board.is_in_check(White));
+        assert_eq!(
+            board.game_result(),
+            Some(GameResult::Checkmate { winner: Black })
+        );
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut board = ChessBoard::new();
+        board.fields[BoardPosition { x: 0, y: 0 }.get_idx()] = Some((King, Black));
+        board.fields[BoardPosition { x: 1, y: 2 }.get_idx()] = Some((Queen, White));
+        board.fields[BoardPosition { x: 7, y: 7 }.get_idx()] = Some((King, White));
+        board.current_player = Black;
+
+        assert!(board.get_legal_moves().is_empty());
+        assert_eq!(board.game_result(), Some(GameResult::Stalemate));
+    }
+
+    #[test]
+    fn starting_position_has_twenty_moves() {
+        assert_eq!(ChessBoard::init_default().get_legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn halfmove_clock_counts() {
+        let mut board = ChessBoard::init_default();
+        assert!(board.move_piece(&BoardPosition { x: 6, y: 7 }, &BoardPosition { x: 5, y: 5 }));
+        assert!(board.move_piece(&BoardPosition { x: 6, y: 0 }, &BoardPosition { x: 5, y: 2 }));
+        assert_eq!(board.halfmove_clock, 2);
+        assert!(board.move_piece(&BoardPosition { x: 4, y: 6 }, &BoardPosition { x: 4, y: 4 }));
+        assert_eq!(board.halfmove_clock, 0);
+
+        board.halfmove_clock = 99;
+        assert_eq!(board.draw_by_rule(), None);
+        assert!(board.move_piece(&BoardPosition { x: 1, y: 0 }, &BoardPosition { x: 2, y: 2 }));
+        assert_eq!(board.game_result(), Some(GameResult::FiftyMoveRule));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let mut board = ChessBoard::new();
+        board.fields[BoardPosition { x: 0, y: 0 }.get_idx()] = Some((King, Black));
+        board.fields[BoardPosition { x: 7, y: 7 }.get_idx()] = Some((King, White));
+        assert!(board.has_insufficient_material());
+
+        board.fields[BoardPosition { x: 2, y: 2 }.get_idx()] = Some((Knight, White));
+        assert!(board.has_insufficient_material());
+
+        // bishops on the same colored squares can't mate
+        board.fields[BoardPosition { x: 2, y: 2 }.get_idx()] = Some((Bishoph, White));
+        board.fields[BoardPosition { x: 3, y: 3 }.get_idx()] = Some((Bishoph, Black));
+        assert!(board.has_insufficient_material());
+        assert_eq!(board.game_result(), Some(GameResult::InsufficientMaterial));
 
-        // now we are good, move it.
-        self.fields[to.get_idx()] = self.fields[from.get_idx()].take();
+        board.fields[BoardPosition { x: 3, y: 3 }.get_idx()] = None;
+        board.fields[BoardPosition { x: 3, y: 2 }.get_idx()] = Some((Bishoph, Black));
+        assert!(!board.has_insufficient_material());
 
-        return true;
+        board.fields[BoardPosition { x: 3, y: 2 }.get_idx()] = Some((Pawn, Black));
+        assert!(!board.has_insufficient_material());
     }
 }
@@ -0,0 +1,100 @@
+use super::{ChessBoard, Move};
+
+impl ChessBoard {
+    /// Counts the leaf nodes of the legal move tree `depth` half moves deep.
+    /// Comparing against known numbers is the standard way to test move generation.
+    pub fn perft(&self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.get_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|chess_move| {
+                let mut moved_board = self.clone();
+                moved_board.apply_move_unchecked(chess_move);
+                moved_board.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    /// Like `perft`, but split up by the first move, to find which subtree disagrees
+    pub fn perft_divide(&self, depth: u8) -> Vec<(Move, u64)> {
+        self.get_legal_moves()
+            .into_iter()
+            .map(|chess_move| {
+                let mut moved_board = self.clone();
+                moved_board.apply_move_unchecked(&chess_move);
+                (chess_move, moved_board.perft(depth.saturating_sub(1)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let board = ChessBoard::from_fen(fen).unwrap();
+        for (depth, &nodes) in expected.iter().enumerate() {
+            assert_eq!(board.perft(depth as u8 + 1), nodes, "depth {}", depth + 1);
+        }
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        assert_perft(ChessBoard::STARTING_FEN, &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn perft_position_3() {
+        assert_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+    }
+
+    #[test]
+    fn perft_position_4() {
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn perft_position_6() {
+        assert_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn divide_adds_up() {
+        let board = ChessBoard::init_default();
+        let divided = board.perft_divide(3);
+        assert_eq!(divided.len(), 20);
+        assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+    }
+}
@@ -0,0 +1,577 @@
+use super::{ChessBoard, Color, FenError, Game, GameResult, Move, SanError};
+use std::fmt;
+
+/// The tags every PGN game should have, in this order
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The game termination marker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Still going, abandoned or unknown
+    Unknown,
+}
+
+impl PgnResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        }
+    }
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<GameResult>> for PgnResult {
+    fn from(result: Option<GameResult>) -> Self {
+        match result {
+            None => Self::Unknown,
+            Some(result) => match result.winner() {
+                Some(Color::White) => Self::WhiteWins,
+                Some(Color::Black) => Self::BlackWins,
+                None => Self::Draw,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub chess_move: Move,
+    /// The `{comment}` following the move
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in the order they are written, the Seven Tag Roster first
+    pub tags: Vec<(String, String)>,
+    /// The comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: PgnResult,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    /// A tag, comment or variation is still open at the end of the text
+    UnexpectedEnd,
+    InvalidTag,
+    UnexpectedCharacter(char),
+    InvalidFen(FenError),
+    IllegalMove(SanError),
+}
+
+/// Where reading a PGN went wrong. Lines and columns start at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            PgnErrorKind::UnexpectedEnd => write!(f, "unexpected end of file"),
+            PgnErrorKind::InvalidTag => write!(f, "invalid tag pair"),
+            PgnErrorKind::UnexpectedCharacter(symbol) => {
+                write!(f, "unexpected character '{symbol}'")
+            }
+            PgnErrorKind::InvalidFen(error) => write!(f, "invalid FEN tag, {error}"),
+            PgnErrorKind::IllegalMove(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl PgnGame {
+    /// An empty game with the Seven Tag Roster filled with placeholders
+    pub fn new() -> Self {
+        Self {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            comment: None,
+            moves: Vec::new(),
+            result: PgnResult::Unknown,
+        }
+    }
+
+    /// Records a game, with the `SetUp` and `FEN` tags if it didn't start from the default position
+    pub fn from_game(game: &Game) -> Self {
+        let mut pgn = Self::new();
+        let initial_fen = game.initial_board().to_fen();
+        if initial_fen != ChessBoard::STARTING_FEN {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", &initial_fen);
+        }
+        pgn.moves = game
+            .moves()
+            .iter()
+            .map(|&chess_move| PgnMove {
+                chess_move,
+                comment: None,
+            })
+            .collect();
+        pgn.set_result(game.result().into());
+        pgn
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+    /// Sets the termination marker and the `Result` tag together
+    pub fn set_result(&mut self, result: PgnResult) {
+        self.result = result;
+        self.set_tag("Result", result.as_str());
+    }
+
+    /// The position the moves start from, taken from the `FEN` tag if there is one
+    pub fn starting_board(&self) -> Result<ChessBoard, FenError> {
+        match self.tag("FEN") {
+            Some(fen) => ChessBoard::from_fen(fen),
+            None => Ok(ChessBoard::init_default()),
+        }
+    }
+
+    /// Plays the moves one by one, stopping at the first one that isn't legal
+    pub fn replay(&self) -> Result<Game, FenError> {
+        let mut game = Game::new(self.starting_board()?);
+        for pgn_move in &self.moves {
+            if !game.play_move(&pgn_move.chess_move) {
+                break;
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("[{name} \"{value}\"]\n"));
+        }
+        out.push('\n');
+
+        let mut tokens = Vec::new();
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{comment}}}"));
+        }
+        let mut board = self
+            .starting_board()
+            .unwrap_or_else(|_| ChessBoard::init_default());
+        // black's moves only need a number at the start or after a comment
+        let mut needs_number = true;
+        for pgn_move in &self.moves {
+            match board.current_player {
+                Color::White => tokens.push(format!("{}.", board.fullmove_number)),
+                Color::Black if needs_number => {
+                    tokens.push(format!("{}...", board.fullmove_number))
+                }
+                Color::Black => {}
+            }
+            tokens.push(board.move_to_san(&pgn_move.chess_move));
+            needs_number = false;
+            if let Some(comment) = &pgn_move.comment {
+                tokens.push(format!("{{{comment}}}"));
+                needs_number = true;
+            }
+            if !board.play_move(&pgn_move.chess_move) {
+                break;
+            }
+        }
+        tokens.push(self.result.as_str().to_string());
+
+        // keep lines below 80 characters
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > 79 {
+                out.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                out.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            out.push_str(&token);
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Reads the first game of a PGN text
+    pub fn parse(text: &str) -> Result<Self, PgnError> {
+        let mut reader = PgnReader::new(text);
+        reader.read_game()?.ok_or(PgnError {
+            line: reader.line,
+            column: reader.column,
+            kind: PgnErrorKind::UnexpectedEnd,
+        })
+    }
+
+    /// Reads every game of a PGN text, like a whole database file
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, PgnError> {
+        let mut reader = PgnReader::new(text);
+        let mut games = Vec::new();
+        while let Some(game) = reader.read_game()? {
+            games.push(game);
+        }
+        Ok(games)
+    }
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct PgnReader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> PgnReader<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let symbol = self.chars.next()?;
+        if symbol == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(symbol)
+    }
+
+    fn error(&self, line: usize, column: usize, kind: PgnErrorKind) -> PgnError {
+        PgnError { line, column, kind }
+    }
+    fn error_here(&self, kind: PgnErrorKind) -> PgnError {
+        self.error(self.line, self.column, kind)
+    }
+
+    /// Skips whitespace, `;` comments and `%` escape lines
+    fn skip_whitespace(&mut self) {
+        while let Some(&symbol) = self.chars.peek() {
+            if symbol == ';' || (symbol == '%' && self.column == 1) {
+                while self.next().is_some_and(|symbol| symbol != '\n') {}
+            } else if symbol.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        self.skip_whitespace();
+        if self.chars.peek().is_none() {
+            return Ok(None);
+        }
+
+        let mut game = PgnGame {
+            tags: Vec::new(),
+            comment: None,
+            moves: Vec::new(),
+            result: PgnResult::Unknown,
+        };
+        while self.chars.peek() == Some(&'[') {
+            let (name, value) = self.read_tag()?;
+            game.tags.push((name, value));
+            self.skip_whitespace();
+        }
+
+        let mut board = game
+            .starting_board()
+            .map_err(|error| self.error_here(PgnErrorKind::InvalidFen(error)))?;
+
+        loop {
+            self.skip_whitespace();
+            let (line, column) = (self.line, self.column);
+            match self.chars.peek() {
+                // the next game starts without a termination marker
+                None | Some('[') => break,
+                Some('{') => {
+                    let comment = self.read_comment()?;
+                    let target = match game.moves.last_mut() {
+                        Some(pgn_move) => &mut pgn_move.comment,
+                        None => &mut game.comment,
+                    };
+                    match target {
+                        Some(existing) => {
+                            existing.push(' ');
+                            existing.push_str(&comment);
+                        }
+                        None => *target = Some(comment),
+                    }
+                }
+                Some('(') => self.skip_variation()?,
+                Some('$') => {
+                    self.next();
+                    while self
+                        .chars
+                        .peek()
+                        .is_some_and(|symbol| symbol.is_ascii_digit())
+                    {
+                        self.next();
+                    }
+                }
+                Some('.') => {
+                    self.next();
+                }
+                Some(&symbol) if is_symbol_char(symbol) || symbol == '*' => {
+                    let token = self.read_symbol();
+                    if let Some(result) = PgnResult::from_token(&token) {
+                        game.result = result;
+                        break;
+                    }
+                    if token.chars().all(|symbol| symbol.is_ascii_digit()) {
+                        // move number, the dots are skipped separately
+                        continue;
+                    }
+                    let chess_move = board.parse_san(&token).map_err(|error| {
+                        self.error(line, column, PgnErrorKind::IllegalMove(error))
+                    })?;
+                    board.play_move(&chess_move);
+                    game.moves.push(PgnMove {
+                        chess_move,
+                        comment: None,
+                    });
+                }
+                Some(&symbol) => {
+                    return Err(self.error_here(PgnErrorKind::UnexpectedCharacter(symbol)))
+                }
+            }
+        }
+        Ok(Some(game))
+    }
+
+    /// `[Name "value"]`
+    fn read_tag(&mut self) -> Result<(String, String), PgnError> {
+        let (line, column) = (self.line, self.column);
+        let invalid = || PgnError {
+            line,
+            column,
+            kind: PgnErrorKind::InvalidTag,
+        };
+        self.next();
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(&symbol) = self.chars.peek() {
+            if !(symbol.is_ascii_alphanumeric() || symbol == '_') {
+                break;
+            }
+            name.push(symbol);
+            self.next();
+        }
+        self.skip_whitespace();
+        if name.is_empty() || self.next() != Some('"') {
+            return Err(invalid());
+        }
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error_here(PgnErrorKind::UnexpectedEnd)),
+                Some('"') => break,
+                Some('\\') => match self.next() {
+                    Some(escaped) => value.push(escaped),
+                    None => return Err(self.error_here(PgnErrorKind::UnexpectedEnd)),
+                },
+                Some(symbol) => value.push(symbol),
+            }
+        }
+        self.skip_whitespace();
+        if self.next() != Some(']') {
+            return Err(invalid());
+        }
+        Ok((name, value))
+    }
+
+    /// `{comment}`, which may span lines
+    fn read_comment(&mut self) -> Result<String, PgnError> {
+        self.next();
+        let mut comment = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error_here(PgnErrorKind::UnexpectedEnd)),
+                Some('}') => break,
+                Some(symbol) => comment.push(symbol),
+            }
+        }
+        Ok(comment.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Variations `( ... )` can be nested, we don't keep them
+    fn skip_variation(&mut self) -> Result<(), PgnError> {
+        let mut depth = 0;
+        loop {
+            match self.chars.peek() {
+                None => return Err(self.error_here(PgnErrorKind::UnexpectedEnd)),
+                Some('{') => {
+                    self.read_comment()?;
+                    continue;
+                }
+                Some('(') => depth += 1,
+                Some(')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.next();
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+            self.next();
+        }
+    }
+
+    fn read_symbol(&mut self) -> String {
+        let mut token = String::new();
+        while let Some(&symbol) = self.chars.peek() {
+            if !(is_symbol_char(symbol) || symbol == '*') {
+                break;
+            }
+            token.push(symbol);
+            self.next();
+        }
+        token
+    }
+}
+
+fn is_symbol_char(symbol: char) -> bool {
+    symbol.is_ascii_alphanumeric() || "_+#=:-/!?".contains(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMMORTAL_GAME: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Lionel Kieseritzky"]
+[Result "1-0"]
+
+{The Immortal Game} 1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 5. Bxb5 Nf6 6. Nf3
+Qh6 7. d3 Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6 13. h5
+Qg5 14. Qf3 Ng8 15. Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 {this is
+where it gets good} 19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8 22. Qf6+ Nxf6 23. Be7# 1-0
+"#;
+
+    #[test]
+    fn reads_a_game() {
+        let game = PgnGame::parse(IMMORTAL_GAME).unwrap();
+        assert_eq!(game.tag("White"), Some("Adolf Anderssen"));
+        assert_eq!(game.comment.as_deref(), Some("The Immortal Game"));
+        assert_eq!(game.moves.len(), 45);
+        assert_eq!(
+            game.moves[35].comment.as_deref(),
+            Some("this is where it gets good")
+        );
+        assert_eq!(game.result, PgnResult::WhiteWins);
+
+        let replayed = game.replay().unwrap();
+        assert_eq!(
+            replayed.result(),
+            Some(GameResult::Checkmate {
+                winner: Color::White
+            })
+        );
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let game = PgnGame::parse(IMMORTAL_GAME).unwrap();
+        let written = game.to_pgn();
+        assert!(written.lines().all(|line| line.len() < 80));
+        let unwrapped = written.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(unwrapped.contains("18. Bd6 Bxg1 {this is where it gets good} 19. e5 Qxa1+"));
+        assert_eq!(PgnGame::parse(&written).unwrap(), game);
+    }
+
+    #[test]
+    fn records_games() {
+        let mut game = Game::new(ChessBoard::from_fen("4k3/8/4K3/8/8/8/8/7R w - - 0 1").unwrap());
+        let board = game.board().clone();
+        assert!(game.play_move(&board.parse_san("Rh8#").unwrap()));
+
+        let pgn = PgnGame::from_game(&game);
+        assert_eq!(pgn.tag("Result"), Some("1-0"));
+        assert_eq!(pgn.tag("FEN"), Some("4k3/8/4K3/8/8/8/8/7R w - - 0 1"));
+        assert!(pgn.to_pgn().ends_with("\n\n1. Rh8# 1-0\n"));
+        assert_eq!(PgnGame::parse(&pgn.to_pgn()).unwrap(), pgn);
+    }
+
+    #[test]
+    fn reads_several_games_with_variations() {
+        let text = "[Event \"a\"]\n\n1. e4 (1. d4 {queen's pawn} d5 (1... Nf6)) e5 $1 *\n\n\
+                    [Event \"b\"]\n\n1. d4 d5 1/2-1/2\n";
+        let games = PgnGame::parse_all(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].moves.len(), 2);
+        assert_eq!(games[0].result, PgnResult::Unknown);
+        assert_eq!(games[1].tag("Event"), Some("b"));
+        assert_eq!(games[1].result, PgnResult::Draw);
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = PgnGame::parse("[Event \"x\"]\n\n1. e4 e5\n2. Ke3 Nc6 *").unwrap_err();
+        assert_eq!((error.line, error.column), (4, 4));
+        assert!(matches!(
+            error.kind,
+            PgnErrorKind::IllegalMove(SanError::NoSuchMove(_))
+        ));
+
+        let error = PgnGame::parse("[Event \"x\"\n\n1. e4 *").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 1));
+        assert_eq!(error.kind, PgnErrorKind::InvalidTag);
+
+        let error = PgnGame::parse("1. e4 {never closed").unwrap_err();
+        assert_eq!(error.kind, PgnErrorKind::UnexpectedEnd);
+    }
+}
@@ -1,9 +1,11 @@
-use super::ChessBoard;
+use super::{CastlingRights, ChessBoard, KNIGHT_JUMPS};
 use ndarray::Array2;
+use std::fmt;
 use std::ops::Not;
+use std::str::FromStr;
 
 #[allow(unused)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ChessPiece {
     Pawn,
     Bishoph,
@@ -13,7 +15,38 @@ pub enum ChessPiece {
     King,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl ChessPiece {
+    /// Every piece, in the order of their discriminants
+    pub const ALL: [ChessPiece; 6] = [
+        ChessPiece::Pawn,
+        ChessPiece::Bishoph,
+        ChessPiece::Rook,
+        ChessPiece::Knight,
+        ChessPiece::Queen,
+        ChessPiece::King,
+    ];
+
+    /// What a pawn may turn into on the last row, best first
+    pub const PROMOTION_CHOICES: [ChessPiece; 4] = [
+        ChessPiece::Queen,
+        ChessPiece::Rook,
+        ChessPiece::Bishoph,
+        ChessPiece::Knight,
+    ];
+
+    /// The usual worth of the piece in pawns. The king can't be traded, so it counts for nothing.
+    pub fn value(&self) -> u8 {
+        match self {
+            ChessPiece::Pawn => 1,
+            ChessPiece::Bishoph | ChessPiece::Knight => 3,
+            ChessPiece::Rook => 5,
+            ChessPiece::Queen => 9,
+            ChessPiece::King => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Color {
     Black,
     White,
@@ -28,7 +61,7 @@ impl Not for Color {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct BoardPosition {
     pub x: u8,
     pub y: u8,
@@ -115,6 +148,53 @@ impl BoardPosition {
             y: row as u8,
         }
     }
+    /// Moves by an absolute offset, independent of the color's point of view
+    pub fn offset(&self, dx: i8, dy: i8) -> Option<BoardPosition> {
+        let x = self.x.checked_add_signed(dx)?;
+        let y = self.y.checked_add_signed(dy)?;
+        let out = BoardPosition { x, y };
+        out.is_in_bounds().then_some(out)
+    }
+    /// The file letter, `a` to `h` from white's left
+    pub fn file(&self) -> char {
+        (b'a' + self.x) as char
+    }
+    /// The rank as chess players count it, 1 is white's home row
+    pub fn rank(&self) -> u8 {
+        8 - self.y
+    }
+}
+
+/// Writes the square in algebraic notation, like `e4`
+impl fmt::Display for BoardPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSquareError(pub String);
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a square", self.0)
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+/// Reads a square in algebraic notation, like `e4`
+impl FromStr for BoardPosition {
+    type Err = ParseSquareError;
+    fn from_str(square: &str) -> Result<Self, Self::Err> {
+        match square.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(BoardPosition {
+                x: file - b'a',
+                y: b'8' - rank,
+            }),
+            _ => Err(ParseSquareError(square.to_string())),
+        }
+    }
 }
 
 pub trait Piece {
@@ -214,7 +294,10 @@ impl Piece for ChessPiece {
         if let Some((piece, color)) = board.get_piece_at_position(&position) {
             match piece {
                 ChessPiece::Pawn => {
-                    // todo: en passant, promote
+                    let is_enemy_pawn = |position: Option<BoardPosition>| {
+                        position.and_then(|position| board.get_piece_at_position(&position))
+                            == Some((ChessPiece::Pawn, !color))
+                    };
                     if let Some(forward) = position.forward(&color, 1) {
                         if board.get_piece_at_position(&forward).is_none() {
                             // we can move forward, it is empty
@@ -223,8 +306,10 @@ impl Piece for ChessPiece {
                             if piece.get_starting_layout()[position.get_idx()].is_some() {
                                 // the piece is still at its starting position
                                 if let Some(two_forward) = position.forward(&color, 2) {
-                                    // we can move two forward
-                                    moves[two_forward.get_idx()] = true;
+                                    // we can move two forward, if that is empty as well
+                                    if board.get_piece_at_position(&two_forward).is_none() {
+                                        moves[two_forward.get_idx()] = true;
+                                    }
                                 }
                             }
                         }
@@ -233,6 +318,8 @@ impl Piece for ChessPiece {
                         if board
                             .get_piece_at_position(&forward_right)
                             .is_some_and(|(_, other_color)| other_color != color)
+                            || board.en_passant == Some(forward_right)
+                                && is_enemy_pawn(forward_right.backward(&color, 1))
                         {
                             // we can capture diagonal right
                             moves[forward_right.get_idx()] = true;
@@ -242,6 +329,8 @@ impl Piece for ChessPiece {
                         if board
                             .get_piece_at_position(&forward_left)
                             .is_some_and(|(_, other_color)| other_color != color)
+                            || board.en_passant == Some(forward_left)
+                                && is_enemy_pawn(forward_left.backward(&color, 1))
                         {
                             // we can capture diagonal left
                             moves[forward_left.get_idx()] = true;
@@ -249,48 +338,17 @@ impl Piece for ChessPiece {
                     }
                 }
                 ChessPiece::Knight => {
-                    let directions = vec![
-                        [
-                            BoardPosition::forward,
-                            BoardPosition::left,
-                            BoardPosition::right,
-                        ],
-                        [
-                            BoardPosition::left,
-                            BoardPosition::forward,
-                            BoardPosition::backward,
-                        ],
-                        [
-                            BoardPosition::right,
-                            BoardPosition::forward,
-                            BoardPosition::backward,
-                        ],
-                        [
-                            BoardPosition::backward,
-                            BoardPosition::left,
-                            BoardPosition::right,
-                        ],
-                    ];
-
-                    // primary direction is the one we move twice (e.g. forward), then we can go either left or right.
-                    // we filter for those out of bounds and then check if it is occupied for the ones left
-                    for [primary_direction, secondary_direction1, secondary_direction2] in
-                        directions
+                    // the same jumps `is_attacked` uses, so moves and attacks can't disagree.
+                    // offsets are absolute, knights move the same for both colors
+                    for position in KNIGHT_JUMPS
+                        .iter()
+                        .filter_map(|&(dx, dy)| position.offset(dx, dy))
                     {
-                        if let Some(pos_after_primary) = primary_direction(&position, &color, 2) {
-                            for position in [
-                                secondary_direction1(&pos_after_primary, &color, 1),
-                                secondary_direction2(&pos_after_primary, &color, 1),
-                            ]
-                            .iter()
-                            .filter_map(|pos| *pos)
-                            {
-                                if !board.get_piece_at_position(&position).is_some_and(
-                                    |(_, color_at_next_pos)| color_at_next_pos == color,
-                                ) {
-                                    moves[position.get_idx()] = true;
-                                }
-                            }
+                        if !board
+                            .get_piece_at_position(&position)
+                            .is_some_and(|(_, color_at_next_pos)| color_at_next_pos == color)
+                        {
+                            moves[position.get_idx()] = true;
                         }
                     }
                 }
@@ -339,6 +397,38 @@ impl Piece for ChessPiece {
                         BoardPosition::diag_fl,
                     ];
                     move_directions(&mut moves, &directions, &position, &board, &color, true);
+
+                    // castling: the king and rook haven't moved, the squares in between are empty
+                    // and the king doesn't start in, pass through or land in check
+                    let home_row = CastlingRights::home_row(color);
+                    if position.y == home_row
+                        && position.x == 4
+                        && !board.is_attacked(position, !color)
+                    {
+                        let can_castle = |rook_x: u8, empty_xs: &[u8], safe_xs: &[u8]| {
+                            board.get_piece_at_position(&BoardPosition {
+                                x: rook_x,
+                                y: home_row,
+                            }) == Some((ChessPiece::Rook, color))
+                                && empty_xs.iter().all(|&x| {
+                                    board
+                                        .get_piece_at_position(&BoardPosition { x, y: home_row })
+                                        .is_none()
+                                })
+                                && safe_xs.iter().all(|&x| {
+                                    !board.is_attacked(&BoardPosition { x, y: home_row }, !color)
+                                })
+                        };
+                        if board.castling_rights.kingside(color) && can_castle(7, &[5, 6], &[5, 6])
+                        {
+                            moves[BoardPosition { x: 6, y: home_row }.get_idx()] = true;
+                        }
+                        if board.castling_rights.queenside(color)
+                            && can_castle(0, &[1, 2, 3], &[2, 3])
+                        {
+                            moves[BoardPosition { x: 2, y: home_row }.get_idx()] = true;
+                        }
+                    }
                 }
             };
         }
@@ -478,4 +568,59 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn knight_moves_correct() {
+        let mut chess_board = ChessBoard::new();
+        let corner = BoardPosition { x: 0, y: 7 };
+        chess_board.fields[corner.get_idx()] = Some((Knight, White));
+        chess_board.fields[BoardPosition { x: 2, y: 6 }.get_idx()] = Some((Pawn, White));
+
+        assert_eq!(
+            ChessPiece::get_moves(&corner, &chess_board),
+            arr2(&[
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, true, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false]
+            ])
+        );
+
+        let edge = BoardPosition { x: 7, y: 3 };
+        chess_board.fields[edge.get_idx()] = Some((Knight, Black));
+        chess_board.fields[BoardPosition { x: 5, y: 2 }.get_idx()] = Some((Pawn, White));
+
+        assert_eq!(
+            ChessPiece::get_moves(&edge, &chess_board),
+            arr2(&[
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, true, false],
+                [false, false, false, false, false, true, false, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, true, false, false],
+                [false, false, false, false, false, false, true, false],
+                [false, false, false, false, false, false, false, false],
+                [false, false, false, false, false, false, false, false]
+            ])
+        );
+    }
+
+    #[test]
+    fn squares_use_algebraic_notation() {
+        assert_eq!(BoardPosition { x: 0, y: 7 }.to_string(), "a1");
+        assert_eq!(BoardPosition { x: 4, y: 4 }.to_string(), "e4");
+        assert_eq!(BoardPosition { x: 7, y: 0 }.to_string(), "h8");
+        assert_eq!("e4".parse(), Ok(BoardPosition { x: 4, y: 4 }));
+        assert_eq!("a8".parse(), Ok(BoardPosition { x: 0, y: 0 }));
+        for invalid in ["", "e", "i1", "a9", "a0", "e44", "E4"] {
+            assert_eq!(
+                invalid.parse::<BoardPosition>(),
+                Err(ParseSquareError(invalid.to_string()))
+            );
+        }
+    }
 }
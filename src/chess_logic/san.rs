@@ -0,0 +1,277 @@
+use super::{BoardPosition, ChessBoard, ChessPiece, Move};
+use std::fmt;
+
+/// Why a move in standard algebraic notation (e.g. `Nf3`, `exd5`, `O-O`) couldn't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// Not SAN at all
+    Malformed(String),
+    /// Valid SAN, but no legal move in the position matches
+    NoSuchMove(String),
+    /// More than one legal move matches
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(san) => write!(f, "'{san}' is not a valid move"),
+            Self::NoSuchMove(san) => write!(f, "'{san}' is not a legal move here"),
+            Self::Ambiguous(san) => write!(f, "'{san}' could mean more than one move"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl ChessPiece {
+    /// The uppercase letter SAN uses for the piece, `None` for pawns
+    pub fn san_symbol(&self) -> Option<char> {
+        match self {
+            ChessPiece::Pawn => None,
+            piece => Some(piece.fen_symbol().to_ascii_uppercase()),
+        }
+    }
+}
+
+impl ChessBoard {
+    /// Writes a legal `chess_move` in standard algebraic notation, including check and mate markers
+    pub fn move_to_san(&self, chess_move: &Move) -> String {
+        let Move { from, to, .. } = chess_move;
+        let Some((piece, color)) = self.get_piece_at_position(from) else {
+            return String::new();
+        };
+
+        let mut san = String::new();
+        if piece == ChessPiece::King && from.x.abs_diff(to.x) == 2 {
+            san.push_str(if to.x > from.x { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = self.get_piece_at_position(to).is_some()
+                || (piece == ChessPiece::Pawn && self.en_passant == Some(*to));
+
+            if let Some(symbol) = piece.san_symbol() {
+                san.push(symbol);
+
+                // other pieces of the same kind that could go there as well
+                let others: Vec<_> = self
+                    .get_legal_moves()
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == *to
+                            && other.from != *from
+                            && self.get_piece_at_position(&other.from) == Some((piece, color))
+                    })
+                    .collect();
+                if !others.is_empty() {
+                    let square = from.to_string();
+                    if others.iter().all(|other| other.from.x != from.x) {
+                        san.push_str(&square[..1]);
+                    } else if others.iter().all(|other| other.from.y != from.y) {
+                        san.push_str(&square[1..]);
+                    } else {
+                        san.push_str(&square);
+                    }
+                }
+            } else if is_capture {
+                san.push(from.file());
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+
+            if self.reaches_last_row(from, to) {
+                san.push('=');
+                san.extend(
+                    chess_move
+                        .promotion
+                        .unwrap_or(ChessPiece::Queen)
+                        .san_symbol(),
+                );
+            }
+        }
+
+        let mut moved_board = self.clone();
+        moved_board.apply_move_unchecked(chess_move);
+        if moved_board.is_in_check(moved_board.current_player) {
+            san.push(if moved_board.get_legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    /// Reads a move in standard algebraic notation. Check markers and annotations like `!?` are ignored.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let malformed = || SanError::Malformed(san.to_string());
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let legal_moves = self.get_legal_moves();
+        let pick = |candidates: Vec<&Move>| match candidates.as_slice() {
+            [chess_move] => Ok(**chess_move),
+            [] => Err(SanError::NoSuchMove(san.to_string())),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        };
+
+        let castling = match trimmed {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = castling {
+            return pick(
+                legal_moves
+                    .iter()
+                    .filter(|chess_move| {
+                        self.get_piece_at_position(&chess_move.from)
+                            .is_some_and(|(piece, _)| piece == ChessPiece::King)
+                            && chess_move.from.x.abs_diff(chess_move.to.x) == 2
+                            && (chess_move.to.x > chess_move.from.x) == kingside
+                    })
+                    .collect(),
+            );
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().collect();
+
+        let piece = match chars.first() {
+            Some(&symbol) if symbol.is_ascii_uppercase() => {
+                chars.remove(0);
+                ChessPiece::from_fen_symbol(symbol.to_ascii_lowercase())
+                    .filter(|&piece| piece != ChessPiece::Pawn)
+                    .ok_or_else(malformed)?
+            }
+            _ => ChessPiece::Pawn,
+        };
+
+        // `e8=Q`, some write `e8Q`
+        let promotion = match chars.last() {
+            Some(&symbol) if symbol.is_ascii_uppercase() => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(
+                    ChessPiece::from_fen_symbol(symbol.to_ascii_lowercase())
+                        .filter(|piece| ChessPiece::PROMOTION_CHOICES.contains(piece))
+                        .ok_or_else(malformed)?,
+                )
+            }
+            _ => None,
+        };
+
+        if chars.len() < 2 {
+            return Err(malformed());
+        }
+        let destination: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to: BoardPosition = destination.parse().map_err(|_| malformed())?;
+
+        // what is left is the disambiguation and the capture marker
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+        let mut from_file = None;
+        let mut from_row = None;
+        for symbol in chars {
+            match symbol {
+                'a'..='h' if from_file.is_none() && from_row.is_none() => {
+                    from_file = Some(symbol as u8 - b'a')
+                }
+                '1'..='8' if from_row.is_none() => from_row = Some(b'8' - symbol as u8),
+                _ => return Err(malformed()),
+            }
+        }
+
+        pick(
+            legal_moves
+                .iter()
+                .filter(|chess_move| {
+                    chess_move.to == to
+                        && chess_move.promotion == promotion
+                        && from_file.is_none_or(|x| chess_move.from.x == x)
+                        && from_row.is_none_or(|y| chess_move.from.y == y)
+                        && self
+                            .get_piece_at_position(&chess_move.from)
+                            .is_some_and(|(moving_piece, _)| moving_piece == piece)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_san(board: &mut ChessBoard, sans: &[&str]) {
+        for san in sans {
+            let chess_move = board.parse_san(san).unwrap();
+            assert_eq!(board.move_to_san(&chess_move), *san);
+            assert!(board.play_move(&chess_move));
+        }
+    }
+
+    #[test]
+    fn san_round_trips() {
+        let mut board = ChessBoard::init_default();
+        play_san(
+            &mut board,
+            &[
+                "e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5", "d4", "c6", "Nf3", "Bg4", "Bf4", "e6",
+                "h3", "Bxf3", "Qxf3", "Bb4", "Be2", "Nd7", "a3", "O-O-O", "axb4", "Qxa1+", "Kd2",
+                "Qxh1",
+            ],
+        );
+    }
+
+    #[test]
+    fn san_disambiguates() {
+        let board = ChessBoard::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        let chess_move = board.parse_san("Rad1").unwrap();
+        assert_eq!(board.move_to_san(&chess_move), "Rad1");
+        assert_eq!(
+            board.parse_san("Rd1"),
+            Err(SanError::Ambiguous("Rd1".to_string()))
+        );
+
+        let board = ChessBoard::from_fen("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1").unwrap();
+        let chess_move = board.parse_san("R1a2").unwrap();
+        assert_eq!(board.move_to_san(&chess_move), "R1a2");
+    }
+
+    #[test]
+    fn san_promotion_and_mate() {
+        let board = ChessBoard::from_fen("k7/4P3/1K6/8/8/8/8/8 w - - 0 1").unwrap();
+        let chess_move = board.parse_san("e8=Q+").unwrap();
+        assert_eq!(chess_move.promotion, Some(ChessPiece::Queen));
+        assert_eq!(board.move_to_san(&chess_move), "e8=Q#");
+        assert_eq!(
+            board.parse_san("e8N").unwrap().promotion,
+            Some(ChessPiece::Knight)
+        );
+        assert!(matches!(
+            board.parse_san("e8"),
+            Err(SanError::NoSuchMove(_))
+        ));
+        assert!(matches!(
+            board.parse_san("e8=K"),
+            Err(SanError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn san_en_passant() {
+        let board =
+            ChessBoard::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3")
+                .unwrap();
+        let chess_move = board.parse_san("exf6").unwrap();
+        assert_eq!(board.move_to_san(&chess_move), "exf6");
+        assert!(matches!(
+            board.parse_san("exd6"),
+            Err(SanError::NoSuchMove(_))
+        ));
+    }
+}
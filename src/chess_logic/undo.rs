@@ -0,0 +1,143 @@
+use super::{BoardPosition, CastlingRights, ChessBoard, ChessPiece, Color, Move};
+
+/// Everything `ChessBoard::make_move` changed that can't be worked out from the board after it.
+/// Handing it back to `unmake_move` restores the board exactly, hash included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Undo {
+    chess_move: Move,
+    /// Before any promotion
+    moved: (ChessPiece, Color),
+    /// With where it stood, which is not where the move ends for en passant
+    captured: Option<(ChessPiece, BoardPosition)>,
+    castling_rights: CastlingRights,
+    en_passant: Option<BoardPosition>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    zobrist_hash: u64,
+}
+
+impl ChessBoard {
+    /// Plays `chess_move` without checking it, so it has to come from `get_legal_moves`,
+    /// `generate_legal_moves` or pass `is_legal`.
+    /// Much cheaper than cloning the board for every move, when the search walks down and back up again.
+    pub fn make_move(&mut self, chess_move: &Move) -> Undo {
+        let undo = Undo {
+            chess_move: *chess_move,
+            moved: self
+                .get_piece_at_position(&chess_move.from)
+                .expect("a move has to start on a piece"),
+            captured: self.captured_piece(chess_move),
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            zobrist_hash: self.zobrist_hash,
+        };
+        self.apply_move_unchecked(chess_move);
+        undo
+    }
+
+    /// Takes back the move `undo` came from. Moves have to be taken back in the reverse order they were made.
+    pub fn unmake_move(&mut self, undo: Undo) {
+        let Move { from, to, .. } = undo.chess_move;
+        let (piece, color) = undo.moved;
+
+        self.fields[to.get_idx()] = None;
+        self.fields[from.get_idx()] = Some(undo.moved);
+        if let Some((captured, position)) = undo.captured {
+            self.fields[position.get_idx()] = Some((captured, !color));
+        }
+        if piece == ChessPiece::King && from.x.abs_diff(to.x) == 2 {
+            let (rook_from, rook_to) = if to.x > from.x { (7, 5) } else { (0, 3) };
+            self.fields[BoardPosition {
+                x: rook_to,
+                y: from.y,
+            }
+            .get_idx()] = None;
+            self.fields[BoardPosition {
+                x: rook_from,
+                y: from.y,
+            }
+            .get_idx()] = Some((ChessPiece::Rook, color));
+        }
+
+        self.current_player = color;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.zobrist_hash = undo.zobrist_hash;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every kind of move: castling both ways, en passant, promotions with and without capture, checks
+    const POSITIONS: [&str; 5] = [
+        ChessBoard::STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+
+    /// Makes and takes back every legal move, and the ones after it, down to `depth`
+    fn assert_round_trips(board: &mut ChessBoard, depth: u8) {
+        let fen = board.to_fen();
+        let before = board.clone();
+        for chess_move in board.get_legal_moves() {
+            let mut played = board.clone();
+            assert!(played.play_move(&chess_move));
+
+            let undo = board.make_move(&chess_move);
+            assert_eq!(board.to_fen(), played.to_fen(), "{fen} {chess_move}");
+            assert_eq!(board.fields, played.fields, "{fen} {chess_move}");
+            assert_eq!(
+                board.zobrist_hash(),
+                played.zobrist_hash(),
+                "{fen} {chess_move}"
+            );
+            if depth > 1 {
+                assert_round_trips(board, depth - 1);
+            }
+            board.unmake_move(undo);
+
+            assert_eq!(board.to_fen(), fen, "{chess_move}");
+            assert_eq!(board.fields, before.fields, "{fen} {chess_move}");
+            assert_eq!(
+                board.zobrist_hash(),
+                before.zobrist_hash(),
+                "{fen} {chess_move}"
+            );
+        }
+    }
+
+    #[test]
+    fn make_and_unmake_round_trip() {
+        for fen in POSITIONS {
+            assert_round_trips(&mut ChessBoard::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn generating_moves_leaves_the_board_alone() {
+        for fen in POSITIONS {
+            let mut board = ChessBoard::from_fen(fen).unwrap();
+            let before = board.clone();
+            let moves = board.generate_legal_moves();
+            assert_eq!(board.to_fen(), fen);
+            assert_eq!(board.fields, before.fields, "{fen}");
+            assert_eq!(board.zobrist_hash(), before.zobrist_hash(), "{fen}");
+
+            // the same moves the full check of every single move lets through
+            let checked: Vec<Move> = board
+                .pseudo_legal_moves()
+                .into_iter()
+                .filter(|chess_move| board.is_legal(chess_move))
+                .collect();
+            assert_eq!(moves, checked, "{fen}");
+        }
+    }
+}
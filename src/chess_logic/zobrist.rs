@@ -0,0 +1,216 @@
+use super::{BoardPosition, CastlingRights, ChessBoard, ChessPiece, Color};
+
+/// Random numbers for every feature of a position. The hash of a position is all of its features xored together,
+/// so a move only has to xor out what it changes and xor in what it creates.
+struct ZobristKeys {
+    /// By piece, then color, then square
+    pieces: [[[u64; 64]; 2]; 6],
+    black_to_move: u64,
+    /// White kingside, white queenside, black kingside, black queenside
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+const KEYS: ZobristKeys = ZobristKeys::generate(0x5EED_C0FF_EE15_600D);
+
+impl ZobristKeys {
+    /// Fills the table with splitmix64, at compile time, so every build hashes the same
+    const fn generate(seed: u64) -> Self {
+        const fn next(state: &mut u64) -> u64 {
+            *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        let mut state = seed;
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 64]; 2]; 6],
+            black_to_move: 0,
+            castling: [0; 4],
+            en_passant_file: [0; 8],
+        };
+        let mut piece = 0;
+        while piece < 6 {
+            let mut color = 0;
+            while color < 2 {
+                let mut square = 0;
+                while square < 64 {
+                    keys.pieces[piece][color][square] = next(&mut state);
+                    square += 1;
+                }
+                color += 1;
+            }
+            piece += 1;
+        }
+        keys.black_to_move = next(&mut state);
+        let mut right = 0;
+        while right < 4 {
+            keys.castling[right] = next(&mut state);
+            right += 1;
+        }
+        let mut file = 0;
+        while file < 8 {
+            keys.en_passant_file[file] = next(&mut state);
+            file += 1;
+        }
+        keys
+    }
+}
+
+pub(super) fn piece_key(piece: ChessPiece, color: Color, position: &BoardPosition) -> u64 {
+    let color = match color {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    KEYS.pieces[piece as usize][color][position.y as usize * 8 + position.x as usize]
+}
+
+pub(super) fn castling_key(rights: CastlingRights) -> u64 {
+    [
+        rights.white_kingside,
+        rights.white_queenside,
+        rights.black_kingside,
+        rights.black_queenside,
+    ]
+    .iter()
+    .zip(KEYS.castling)
+    .filter(|(allowed, _)| **allowed)
+    .fold(0, |hash, (_, key)| hash ^ key)
+}
+
+pub(super) fn en_passant_key(en_passant: Option<BoardPosition>) -> u64 {
+    en_passant.map_or(0, |position| KEYS.en_passant_file[position.x as usize])
+}
+
+pub(super) fn side_key(color: Color) -> u64 {
+    match color {
+        Color::White => 0,
+        Color::Black => KEYS.black_to_move,
+    }
+}
+
+impl ChessBoard {
+    /// Identifies the position, for repetitions and the transposition table: the pieces, whose turn it is,
+    /// castling rights and en passant, but not the move counters
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+
+    /// Hashes the position from scratch. Needed after editing the public fields directly,
+    /// moves keep the hash up to date on their own.
+    pub fn refresh_zobrist_hash(&mut self) {
+        let mut hash = side_key(self.current_player)
+            ^ castling_key(self.castling_rights)
+            ^ en_passant_key(self.en_passant);
+        for (piece, color, position) in self.get_all_pieces_and_positions() {
+            hash ^= piece_key(piece, color, &position);
+        }
+        self.zobrist_hash = hash;
+    }
+
+    /// Puts `field` on `position`, updating the hash
+    pub(super) fn set_field(
+        &mut self,
+        position: &BoardPosition,
+        field: Option<(ChessPiece, Color)>,
+    ) {
+        if let Some((piece, color)) = self.fields[position.get_idx()] {
+            self.zobrist_hash ^= piece_key(piece, color, position);
+        }
+        if let Some((piece, color)) = field {
+            self.zobrist_hash ^= piece_key(piece, color, position);
+        }
+        self.fields[position.get_idx()] = field;
+    }
+
+    /// Runs `change` on the castling rights, en passant square and side to move, updating the hash
+    pub(super) fn update_state(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = side_key(self.current_player)
+            ^ castling_key(self.castling_rights)
+            ^ en_passant_key(self.en_passant);
+        change(self);
+        let after = side_key(self.current_player)
+            ^ castling_key(self.castling_rights)
+            ^ en_passant_key(self.en_passant);
+        self.zobrist_hash ^= before ^ after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    /// Walks down the first few moves of every position and checks the updated hash against a fresh one
+    fn assert_hash_follows_moves(board: &ChessBoard, depth: u8) {
+        let mut fresh = board.clone();
+        fresh.refresh_zobrist_hash();
+        assert_eq!(
+            board.zobrist_hash(),
+            fresh.zobrist_hash(),
+            "{}",
+            board.to_fen()
+        );
+        if depth == 0 {
+            return;
+        }
+        for chess_move in board.get_legal_moves() {
+            let mut moved_board = board.clone();
+            assert!(moved_board.play_move(&chess_move));
+            assert_hash_follows_moves(&moved_board, depth - 1);
+        }
+    }
+
+    #[test]
+    fn hash_follows_moves() {
+        for fen in [
+            ChessBoard::STARTING_FEN,
+            // castling both ways, captures of castling rooks, promotions
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            // en passant
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            assert_hash_follows_moves(&ChessBoard::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn transpositions_hash_the_same() {
+        let play = |moves: &[&str]| {
+            let mut board = ChessBoard::init_default();
+            for uci in moves {
+                assert!(board.play_move(&uci.parse::<Move>().unwrap()));
+            }
+            board.zobrist_hash()
+        };
+        assert_eq!(
+            play(&["g1f3", "g8f6", "b1c3"]),
+            play(&["b1c3", "g8f6", "g1f3"])
+        );
+        assert_eq!(
+            play(&["g1f3", "g8f6", "f3g1", "f6g8"]),
+            ChessBoard::init_default().zobrist_hash()
+        );
+    }
+
+    #[test]
+    fn hash_tells_state_apart() {
+        let hash = |fen| ChessBoard::from_fen(fen).unwrap().zobrist_hash();
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        for other in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b Kkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+        ] {
+            assert_ne!(hash(other), hash(after_e4), "{other}");
+        }
+        // the move counters don't matter
+        assert_eq!(
+            hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 7 30"),
+            hash(after_e4)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+mod uci;
+pub use uci::Uci;
+
+mod xboard;
+pub use xboard::Xboard;
+
+use crate::{ChessBoard, Model, SearchInfo, SearchLimits, TranspositionTable};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// A search running on its own thread, so the protocol can keep reading commands meanwhile
+struct SearchThread {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl SearchThread {
+    /// Both callbacks run on the search thread, `on_info` after every depth and
+    /// `on_done` once the search is over, stopped or not
+    fn start(
+        model: Arc<Model>,
+        table: Arc<TranspositionTable>,
+        board: ChessBoard,
+        limits: SearchLimits,
+        on_info: impl FnMut(&SearchInfo) + Send + 'static,
+        on_done: impl FnOnce(Option<SearchInfo>) + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            on_done(model.search_with_table(&board, &limits, &table, &thread_stop, on_info))
+        });
+        Self { stop, handle }
+    }
+
+    /// Ends the search early and waits until `on_done` has run
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().expect("search thread panicked");
+    }
+}
+
+/// Output the tests can read back while the search thread still holds on to it
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl SharedBuffer {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
@@ -0,0 +1,279 @@
+use super::SearchThread;
+use crate::{
+    ChessBoard, Clock, Color, Model, Move, ParseMoveError, SearchInfo, SearchLimits,
+    TranspositionTable,
+};
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Plays a `Model` over the Universal Chess Interface, the text protocol most chess GUIs and match runners speak
+pub struct Uci<W> {
+    model: Arc<Model>,
+    /// Kept from one search to the next, until the game changes
+    table: Arc<TranspositionTable>,
+    board: ChessBoard,
+    /// Shared with the search thread, which reports its results on its own
+    out: Arc<Mutex<W>>,
+    search: Option<SearchThread>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(model: Model, out: W) -> Self {
+        Self {
+            model: Arc::new(model),
+            table: Arc::default(),
+            board: ChessBoard::init_default(),
+            out: Arc::new(Mutex::new(out)),
+            search: None,
+        }
+    }
+
+    /// Answers the commands in `input` until it ends or sends `quit`
+    pub fn run(&mut self, input: impl BufRead) -> io::Result<()> {
+        for line in input.lines() {
+            if !self.handle_command(&line?)? {
+                break;
+            }
+        }
+        self.stop_search();
+        Ok(())
+    }
+
+    /// Answers a single command, returns `false` once the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["uci", ..] => {
+                self.send(concat!("id name ", env!("CARGO_PKG_NAME")))?;
+                self.send("id author Ceedrich")?;
+                self.send("uciok")?;
+            }
+            ["isready", ..] => self.send("readyok")?,
+            ["ucinewgame", ..] => {
+                self.stop_search();
+                self.table.clear();
+                self.board = ChessBoard::init_default();
+            }
+            ["position", args @ ..] => {
+                self.stop_search();
+                match parse_position(args) {
+                    Ok(board) => self.board = board,
+                    Err(message) => self.send(&format!("info string {message}"))?,
+                }
+            }
+            ["go", args @ ..] => {
+                self.stop_search();
+                self.start_search(self.parse_limits(args));
+            }
+            ["stop", ..] => self.stop_search(),
+            ["quit", ..] => return Ok(false),
+            // the protocol says to ignore anything unknown
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        let mut out = self.out.lock().unwrap();
+        writeln!(out, "{line}")?;
+        out.flush()
+    }
+
+    fn parse_limits(&self, args: &[&str]) -> SearchLimits {
+        let mut limits = SearchLimits::default();
+        let mut clock = None;
+        let mut increment = Duration::ZERO;
+        let mut moves_to_go = None;
+        for pair in args.windows(2) {
+            let milliseconds = || pair[1].parse().ok().map(Duration::from_millis);
+            match (pair[0], self.board.current_player) {
+                ("depth", _) => limits.depth = pair[1].parse().ok(),
+                ("nodes", _) => limits.nodes = pair[1].parse().ok(),
+                ("movetime", _) => limits.movetime = milliseconds(),
+                ("movestogo", _) => moves_to_go = pair[1].parse().ok(),
+                ("wtime", Color::White) | ("btime", Color::Black) => clock = milliseconds(),
+                ("winc", Color::White) | ("binc", Color::Black) => {
+                    increment = milliseconds().unwrap_or_default()
+                }
+                _ => {}
+            }
+        }
+        limits.clock = clock.map(|remaining| Clock {
+            remaining,
+            increment,
+            moves_to_go,
+        });
+        limits
+    }
+
+    fn start_search(&mut self, limits: SearchLimits) {
+        let info_out = self.out.clone();
+        let done_out = self.out.clone();
+        // there is nobody to tell if the GUI went away
+        self.search = Some(SearchThread::start(
+            self.model.clone(),
+            self.table.clone(),
+            self.board.clone(),
+            limits,
+            move |info| {
+                let _ = report_info(&mut *info_out.lock().unwrap(), info);
+            },
+            move |info| {
+                let _ = report_best_move(&mut *done_out.lock().unwrap(), info.as_ref());
+            },
+        ));
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop();
+        }
+    }
+}
+
+fn report_info(out: &mut impl Write, info: &SearchInfo) -> io::Result<()> {
+    let pv: Vec<String> = info.pv.iter().map(Move::to_string).collect();
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {}", (info.score * 100.0).round() as i64),
+    };
+    writeln!(
+        out,
+        "info depth {} score {score} nodes {} time {} pv {}",
+        info.depth,
+        info.nodes,
+        info.time.as_millis(),
+        pv.join(" ")
+    )?;
+    out.flush()
+}
+
+fn report_best_move(out: &mut impl Write, info: Option<&SearchInfo>) -> io::Result<()> {
+    match info {
+        Some(info) => writeln!(out, "bestmove {}", info.pv[0])?,
+        // mate or stalemate, UCI's null move
+        None => writeln!(out, "bestmove 0000")?,
+    }
+    out.flush()
+}
+
+/// Reads the arguments of `position`: `startpos` or `fen <fen>`, optionally followed by `moves <move>...`
+fn parse_position(args: &[&str]) -> Result<ChessBoard, String> {
+    let moves_start = args
+        .iter()
+        .position(|&word| word == "moves")
+        .unwrap_or(args.len());
+    let mut board = match &args[..moves_start] {
+        ["startpos"] => ChessBoard::init_default(),
+        ["fen", fen @ ..] => {
+            ChessBoard::from_fen(&fen.join(" ")).map_err(|error| error.to_string())?
+        }
+        _ => return Err("expected 'startpos' or 'fen <fen>'".to_string()),
+    };
+    for &uci in args.iter().skip(moves_start + 1) {
+        let chess_move: Move = uci
+            .parse()
+            .map_err(|error: ParseMoveError| error.to_string())?;
+        if !board.play_move(&chess_move) {
+            return Err(format!("illegal move {uci}"));
+        }
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::SharedBuffer;
+
+    #[test]
+    fn uci_handshake() {
+        let out = SharedBuffer::default();
+        let mut uci = Uci::new(Model::default(), out.clone());
+        uci.run("uci\nisready\nquit\nisready\n".as_bytes()).unwrap();
+        let text = out.text();
+        assert!(text.contains("id name "));
+        assert!(text.ends_with("uciok\nreadyok\n"));
+    }
+
+    #[test]
+    fn uci_sets_up_positions() {
+        let mut uci = Uci::new(Model::default(), io::sink());
+        uci.handle_command("position startpos moves e2e4 e7e5 g1f3")
+            .unwrap();
+        assert_eq!(
+            uci.board.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        uci.handle_command("position fen 4k3/P7/8/8/8/8/8/4K3 w - - 0 1 moves a7a8n")
+            .unwrap();
+        assert_eq!(uci.board.to_fen(), "N3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        // a bad position leaves the last one in place
+        uci.handle_command("position startpos moves e2e5").unwrap();
+        assert_eq!(uci.board.to_fen(), "N3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn uci_reads_time_controls() {
+        let mut uci = Uci::new(Model::default(), io::sink());
+        assert_eq!(
+            uci.parse_limits(&["wtime", "30000", "btime", "60000", "winc", "500", "depth", "3"]),
+            SearchLimits {
+                depth: Some(3),
+                clock: Some(Clock {
+                    remaining: Duration::from_secs(30),
+                    increment: Duration::from_millis(500),
+                    moves_to_go: None,
+                }),
+                ..Default::default()
+            }
+        );
+        uci.handle_command("position startpos moves e2e4").unwrap();
+        assert_eq!(
+            uci.parse_limits(&["wtime", "30000", "btime", "60000", "movestogo", "12"])
+                .clock,
+            Some(Clock {
+                remaining: Duration::from_secs(60),
+                increment: Duration::ZERO,
+                moves_to_go: Some(12),
+            })
+        );
+        assert_eq!(
+            uci.parse_limits(&["movetime", "500", "nodes", "10000"]),
+            SearchLimits {
+                movetime: Some(Duration::from_millis(500)),
+                nodes: Some(10000),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn uci_searches_in_the_background() {
+        let out = SharedBuffer::default();
+        let mut uci = Uci::new(Model::default(), out.clone());
+        uci.handle_command("go depth 2").unwrap();
+        uci.search.take().unwrap().handle.join().unwrap();
+
+        let text = out.text();
+        assert!(text.starts_with("info depth 1 score cp "), "{text}");
+        assert!(text.contains("\ninfo depth 2 score cp "), "{text}");
+        let best_move: Move = text
+            .lines()
+            .last()
+            .and_then(|line| line.strip_prefix("bestmove "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(ChessBoard::init_default().is_legal(&best_move));
+
+        uci.handle_command("position fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1")
+            .unwrap();
+        uci.handle_command("go").unwrap();
+        uci.handle_command("stop").unwrap();
+        assert!(out.text().ends_with("bestmove 0000\n"));
+    }
+}
@@ -0,0 +1,473 @@
+use super::SearchThread;
+use crate::{
+    ChessBoard, Clock, Color, Game, GameResult, Model, Move, PgnResult, SearchInfo, SearchLimits,
+    TranspositionTable,
+};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Commands the engine accepts but has no use for
+const IGNORED_COMMANDS: [&str; 10] = [
+    "accepted", "rejected", "random", "hard", "easy", "computer", "otim", "name", "rating", "ics",
+];
+
+/// Plays a `Model` over the Chess Engine Communication Protocol (version 2), the protocol of XBoard and WinBoard
+pub struct Xboard<W> {
+    model: Arc<Model>,
+    /// Kept from one search to the next, until the game changes
+    table: Arc<TranspositionTable>,
+    /// Shared with the search thread, which plays the move it finds
+    game: Arc<Mutex<Game>>,
+    out: Arc<Mutex<W>>,
+    search: Option<XboardSearch>,
+    /// The side the engine plays, `None` in force mode
+    engine_color: Option<Color>,
+    /// Whether to print thinking output
+    post: bool,
+    /// Set by `sd`
+    depth: Option<u8>,
+    /// Set by `st`
+    move_time: Option<Duration>,
+    /// Moves per session, 0 if the whole game is one session, and the increment, both set by `level`
+    level: Option<(u32, Duration)>,
+    /// The engine's clock, set by `level` and kept up to date by `time`
+    clock: Option<Duration>,
+}
+
+struct XboardSearch {
+    thread: SearchThread,
+    /// Tells the search thread to throw away its move instead of playing it
+    discard: Arc<AtomicBool>,
+}
+
+impl<W: Write + Send + 'static> Xboard<W> {
+    pub fn new(model: Model, out: W) -> Self {
+        Self {
+            model: Arc::new(model),
+            table: Arc::default(),
+            game: Arc::new(Mutex::new(Game::new(ChessBoard::init_default()))),
+            out: Arc::new(Mutex::new(out)),
+            search: None,
+            engine_color: Some(Color::Black),
+            post: false,
+            depth: None,
+            move_time: None,
+            level: None,
+            clock: None,
+        }
+    }
+
+    /// Answers the commands in `input` until it ends or sends `quit`
+    pub fn run(&mut self, input: impl BufRead) -> io::Result<()> {
+        for line in input.lines() {
+            if !self.handle_command(&line?)? {
+                break;
+            }
+        }
+        self.cancel_search();
+        Ok(())
+    }
+
+    /// Answers a single command, returns `false` once the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["xboard"] => {}
+            ["protover", ..] => self.send(concat!(
+                "feature myname=\"",
+                env!("CARGO_PKG_NAME"),
+                "\" setboard=1 usermove=1 ping=1 colors=0 analyze=0 sigint=0 sigterm=0 done=1"
+            ))?,
+            ["new"] => {
+                self.cancel_search();
+                self.table.clear();
+                *self.game.lock().unwrap() = Game::new(ChessBoard::init_default());
+                self.engine_color = Some(Color::Black);
+                self.depth = None;
+            }
+            ["force"] => {
+                self.cancel_search();
+                self.engine_color = None;
+            }
+            ["go"] => {
+                self.cancel_search();
+                self.engine_color = Some(self.game.lock().unwrap().board().current_player);
+                self.start_search();
+            }
+            ["?"] => self.move_now(),
+            ["usermove", uci] => {
+                self.cancel_search();
+                let played = uci
+                    .parse::<Move>()
+                    .is_ok_and(|chess_move| self.game.lock().unwrap().play_move(&chess_move));
+                let result = self.game.lock().unwrap().result();
+                if !played {
+                    self.send(&format!("Illegal move: {uci}"))?;
+                } else if let Some(result) = result {
+                    self.send(&result_line(result))?;
+                } else {
+                    self.start_search();
+                }
+            }
+            ["setboard", fen @ ..] => {
+                self.cancel_search();
+                match ChessBoard::from_fen(&fen.join(" ")) {
+                    Ok(board) => *self.game.lock().unwrap() = Game::new(board),
+                    Err(error) => self.send(&format!("tellusererror Illegal position: {error}"))?,
+                }
+            }
+            ["level", moves_per_session, base, increment] => {
+                match parse_level(moves_per_session, base, increment) {
+                    Some((moves_per_session, base, increment)) => {
+                        self.level = Some((moves_per_session, increment));
+                        self.clock = Some(base);
+                        self.move_time = None;
+                    }
+                    None => self.send(&format!("Error (bad time control): {line}"))?,
+                }
+            }
+            ["st", seconds] => match seconds
+                .parse()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            {
+                Some(move_time) => self.move_time = Some(move_time),
+                None => self.send(&format!("Error (bad time control): {line}"))?,
+            },
+            ["sd", depth] => match depth.parse() {
+                Ok(depth) => self.depth = Some(depth),
+                Err(_) => self.send(&format!("Error (bad depth): {line}"))?,
+            },
+            ["time", centiseconds] => {
+                if let Ok(centiseconds) = centiseconds.parse::<u64>() {
+                    self.clock = Some(Duration::from_millis(centiseconds.saturating_mul(10)));
+                }
+            }
+            ["post"] => self.post = true,
+            ["nopost"] => self.post = false,
+            ["ping", number] => self.send(&format!("pong {number}"))?,
+            ["result", ..] => {
+                self.cancel_search();
+                self.engine_color = None;
+            }
+            ["quit"] => return Ok(false),
+            [command, ..] if IGNORED_COMMANDS.contains(command) => {}
+            _ => self.send(&format!("Error (unknown command): {line}"))?,
+        }
+        Ok(true)
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        let mut out = self.out.lock().unwrap();
+        writeln!(out, "{line}")?;
+        out.flush()
+    }
+
+    fn limits(&self) -> SearchLimits {
+        let (moves_per_session, increment) = self.level.unwrap_or_default();
+        // the moves the engine already made this session
+        let moves_made = self.game.lock().unwrap().board().fullmove_number - 1;
+        SearchLimits {
+            depth: self.depth,
+            movetime: self.move_time,
+            clock: self.clock.map(|remaining| Clock {
+                remaining,
+                increment,
+                moves_to_go: (moves_per_session > 0)
+                    .then(|| moves_per_session - moves_made % moves_per_session),
+            }),
+            nodes: None,
+        }
+    }
+
+    /// Starts thinking if it's the engine's turn and the game isn't over yet
+    fn start_search(&mut self) {
+        let limits = self.limits();
+        let game = self.game.lock().unwrap();
+        if self.engine_color != Some(game.board().current_player) || game.result().is_some() {
+            return;
+        }
+        let discard = Arc::new(AtomicBool::new(false));
+        let post = self.post;
+        let info_out = self.out.clone();
+        let shared_game = self.game.clone();
+        let done_out = self.out.clone();
+        let thread_discard = discard.clone();
+        // there is nobody to tell if the GUI went away
+        let thread = SearchThread::start(
+            self.model.clone(),
+            self.table.clone(),
+            game.board().clone(),
+            limits,
+            move |info| {
+                if post {
+                    let _ = writeln!(info_out.lock().unwrap(), "{}", thinking_line(info));
+                }
+            },
+            move |info| {
+                let mut game = shared_game.lock().unwrap();
+                if !thread_discard.load(Ordering::Relaxed) {
+                    let _ = play(&mut game, &mut *done_out.lock().unwrap(), info.as_ref());
+                }
+            },
+        );
+        self.search = Some(XboardSearch { thread, discard });
+    }
+
+    /// Cuts the search short, but still plays the move
+    fn move_now(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.thread.stop();
+        }
+    }
+
+    fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.discard.store(true, Ordering::Relaxed);
+            search.thread.stop();
+        }
+    }
+}
+
+/// Reads `level`'s arguments. The base time is either minutes or `minutes:seconds`, the increment is in seconds.
+fn parse_level(
+    moves_per_session: &str,
+    base: &str,
+    increment: &str,
+) -> Option<(u32, Duration, Duration)> {
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+    let base = minutes
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(60)?
+        .checked_add(seconds.parse().ok()?)?;
+    Some((
+        moves_per_session.parse().ok()?,
+        Duration::from_secs(base),
+        Duration::try_from_secs_f64(increment.parse().ok()?).ok()?,
+    ))
+}
+
+/// The thinking output XBoard shows after `post`: depth, score, time in centiseconds, nodes and the expected line
+fn thinking_line(info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(Move::to_string).collect();
+    // XBoard's convention for mates is 100000 plus the moves until it happens
+    let score = match info.mate_in() {
+        Some(moves) => moves.signum() as i64 * (100000 + moves.abs() as i64),
+        None => (info.score * 100.0).round() as i64,
+    };
+    format!(
+        "{} {score} {} {} {}",
+        info.depth,
+        info.time.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    )
+}
+
+/// Plays the engine's move, or claims the result if there is none
+fn play(game: &mut Game, out: &mut impl Write, info: Option<&SearchInfo>) -> io::Result<()> {
+    if let Some(info) = info {
+        game.play_move(&info.pv[0]);
+        writeln!(out, "move {}", info.pv[0])?;
+    }
+    if let Some(result) = game.result() {
+        writeln!(out, "{}", result_line(result))?;
+    }
+    out.flush()
+}
+
+/// How the game ended, like `1-0 {White mates}`
+fn result_line(result: GameResult) -> String {
+    let reason = match result {
+        GameResult::Checkmate {
+            winner: Color::White,
+        } => "White mates",
+        GameResult::Checkmate {
+            winner: Color::Black,
+        } => "Black mates",
+        GameResult::Stalemate => "Stalemate",
+        GameResult::FiftyMoveRule => "Draw by fifty-move rule",
+        GameResult::ThreefoldRepetition => "Draw by repetition",
+        GameResult::InsufficientMaterial => "Draw by insufficient material",
+    };
+    format!("{} {{{reason}}}", PgnResult::from(Some(result)).as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::SharedBuffer;
+
+    fn wait_for_move<W: Write + Send + 'static>(xboard: &mut Xboard<W>) {
+        xboard.search.take().unwrap().thread.handle.join().unwrap();
+    }
+
+    #[test]
+    fn xboard_handshake() {
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        xboard
+            .run("xboard\nprotover 2\nping 7\nfoo\nquit\nping 8\n".as_bytes())
+            .unwrap();
+        let text = out.text();
+        assert!(text.starts_with("feature myname="), "{text}");
+        assert!(text.contains(" usermove=1 "), "{text}");
+        assert!(
+            text.ends_with("pong 7\nError (unknown command): foo\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn xboard_answers_user_moves() {
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        for command in ["new", "sd 1", "usermove e2e4"] {
+            xboard.handle_command(command).unwrap();
+        }
+        wait_for_move(&mut xboard);
+
+        let reply: Move = out
+            .text()
+            .strip_prefix("move ")
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        let game = xboard.game.lock().unwrap();
+        assert_eq!(game.moves()[1], reply);
+        assert_eq!(game.board().current_player, Color::White);
+    }
+
+    #[test]
+    fn xboard_force_mode_only_records_moves() {
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        for command in [
+            "new",
+            "force",
+            "usermove e2e4",
+            "usermove e7e5",
+            "usermove e2e4",
+        ] {
+            xboard.handle_command(command).unwrap();
+        }
+        assert!(xboard.search.is_none());
+        assert_eq!(out.text(), "Illegal move: e2e4\n");
+
+        xboard.handle_command("sd 1").unwrap();
+        xboard.handle_command("go").unwrap();
+        wait_for_move(&mut xboard);
+        assert_eq!(xboard.engine_color, Some(Color::White));
+        assert_eq!(xboard.game.lock().unwrap().moves().len(), 3);
+    }
+
+    #[test]
+    fn xboard_claims_the_result() {
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        xboard
+            .handle_command("setboard 7k/8/6K1/8/8/8/8/1Q6 w - - 0 1")
+            .unwrap();
+        xboard.handle_command("usermove b1b8").unwrap();
+        assert!(xboard.search.is_none());
+        assert_eq!(out.text(), "1-0 {White mates}\n");
+
+        xboard
+            .handle_command("setboard 7k/8/8/8/8/8/8 w - - 0 1")
+            .unwrap();
+        assert!(out.text().ends_with(
+            "tellusererror Illegal position: piece placement: expected 8 rows, found 7\n"
+        ));
+    }
+
+    #[test]
+    fn xboard_reads_time_controls() {
+        let mut xboard = Xboard::new(Model::default(), io::sink());
+        xboard.handle_command("level 40 5 0").unwrap();
+        assert_eq!(
+            xboard.limits().clock,
+            Some(Clock {
+                remaining: Duration::from_secs(300),
+                increment: Duration::ZERO,
+                moves_to_go: Some(40),
+            })
+        );
+        xboard
+            .handle_command("setboard 4k3/8/8/8/8/8/8/4K3 w - - 0 45")
+            .unwrap();
+        xboard.handle_command("time 6000").unwrap();
+        assert_eq!(
+            xboard.limits().clock,
+            Some(Clock {
+                remaining: Duration::from_secs(60),
+                increment: Duration::ZERO,
+                moves_to_go: Some(36),
+            })
+        );
+
+        xboard.handle_command("level 0 2:30 1.5").unwrap();
+        assert_eq!(
+            xboard.limits().clock,
+            Some(Clock {
+                remaining: Duration::from_secs(150),
+                increment: Duration::from_millis(1500),
+                moves_to_go: None,
+            })
+        );
+        xboard.handle_command("st 0.5").unwrap();
+        xboard.handle_command("sd 3").unwrap();
+        let limits = xboard.limits();
+        assert_eq!(limits.depth, Some(3));
+        assert_eq!(limits.movetime, Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn xboard_survives_huge_time_controls() {
+        for commands in [
+            ["st 1e19", "go"],
+            ["level 40 5 1e19", "go"],
+            ["time 18446744073709551615", "go"],
+        ] {
+            let out = SharedBuffer::default();
+            let mut xboard = Xboard::new(Model::default(), out.clone());
+            for command in ["new", "sd 1"].into_iter().chain(commands) {
+                xboard.handle_command(command).unwrap();
+            }
+            wait_for_move(&mut xboard);
+            assert!(out.text().starts_with("move "), "{commands:?}");
+        }
+
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        // 60 times the minutes don't fit into seconds
+        xboard
+            .handle_command("level 40 307445734561825861 0")
+            .unwrap();
+        assert_eq!(
+            out.text(),
+            "Error (bad time control): level 40 307445734561825861 0\n"
+        );
+        assert_eq!(xboard.limits().clock, None);
+    }
+
+    #[test]
+    fn xboard_rejects_bad_time_controls() {
+        let out = SharedBuffer::default();
+        let mut xboard = Xboard::new(Model::default(), out.clone());
+        for command in ["st -1", "st NaN", "st 1e300", "st soon"] {
+            xboard.handle_command(command).unwrap();
+        }
+        assert_eq!(
+            out.text(),
+            "Error (bad time control): st -1\n\
+             Error (bad time control): st NaN\n\
+             Error (bad time control): st 1e300\n\
+             Error (bad time control): st soon\n"
+        );
+        assert_eq!(xboard.limits().movetime, None);
+    }
+}
@@ -1,37 +1,53 @@
 use crate::{
-    BoardPosition, ChessBoard,
+    BoardPosition, ChessBoard, Game, GameResult,
     {gui::TupleWrapper, Color},
 };
 
-use fltk::{button::Button, prelude::*};
+use fltk::{button::Button, dialog, prelude::*};
 use ndarray::Array2;
 use std::sync::{Arc, RwLock};
 
 pub struct GameState {
-    pub board: ChessBoard,
-    pub current_player: Color,
+    pub game: Game,
     pub needs_redraw: bool,
     pub position_from: Option<BoardPosition>,
+    pub result: Option<GameResult>,
 }
 impl GameState {
     fn new(board: ChessBoard) -> Self {
         Self {
-            board,
-            current_player: Color::White,
+            game: Game::new(board),
             needs_redraw: true,
             position_from: None,
+            result: None,
         }
     }
     pub fn new_arc(board: ChessBoard) -> Arc<RwLock<Self>> {
         Arc::new(RwLock::new(Self::new(board)))
     }
 
+    pub fn result_text(&self) -> Option<String> {
+        self.result.map(|result| match result {
+            GameResult::Checkmate {
+                winner: Color::White,
+            } => "Checkmate, white wins".to_string(),
+            GameResult::Checkmate {
+                winner: Color::Black,
+            } => "Checkmate, black wins".to_string(),
+            GameResult::Stalemate => "Stalemate, it's a draw".to_string(),
+            GameResult::FiftyMoveRule => "Draw by the fifty-move rule".to_string(),
+            GameResult::ThreefoldRepetition => "Draw by threefold repetition".to_string(),
+            GameResult::InsufficientMaterial => "Draw by insufficient material".to_string(),
+        })
+    }
+
     pub fn tick(&mut self, button_matrix: &mut Array2<(Button, (usize, usize))>) {
         if self.needs_redraw {
             self.needs_redraw = false;
             button_matrix.map_mut(|(but, (row, col))| {
                 but.set_image(
-                    self.board
+                    self.game
+                        .board()
                         .get_piece_at_position(&BoardPosition::from_idx(*row, *col))
                         .and_then(|piece_color| {
                             TupleWrapper::from(piece_color).into_shared_image()
@@ -39,6 +55,9 @@ impl GameState {
                 );
                 but.redraw();
             });
+            if let Some(text) = self.result_text() {
+                dialog::message_default(&text);
+            }
         }
     }
 }
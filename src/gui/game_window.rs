@@ -1,6 +1,15 @@
 use super::game_state::GameState;
-use crate::{BoardPosition, ChessBoard};
-use fltk::{app, button::Button, image::PngImage, prelude::*, window::Window};
+use crate::{BoardPosition, ChessBoard, ChessPiece, Move, PgnGame};
+use fltk::{
+    app,
+    button::Button,
+    dialog,
+    enums::{Event, Key},
+    image::PngImage,
+    menu::MenuItem,
+    prelude::*,
+    window::Window,
+};
 use ndarray::Array2;
 use std::sync::{Arc, RwLock};
 
@@ -27,6 +36,27 @@ impl GameWindow {
 
         let button_matrix = Self::initialize_button_matrix(state.clone());
 
+        // ctrl+s saves the game as PGN, ctrl+o loads one
+        let shortcut_state = state.clone();
+        window.handle(move |_, event| {
+            if event != Event::KeyDown || !app::is_event_ctrl() {
+                return false;
+            }
+            if app::event_key() == Key::from_char('s') {
+                if let Ok(game_state) = shortcut_state.read() {
+                    Self::save_pgn(&game_state);
+                }
+                true
+            } else if app::event_key() == Key::from_char('o') {
+                if let Ok(mut game_state) = shortcut_state.write() {
+                    Self::load_pgn(&mut game_state);
+                }
+                true
+            } else {
+                false
+            }
+        });
+
         window.end();
         Self {
             app,
@@ -55,7 +85,7 @@ impl GameWindow {
                 row as i32 * cell_size,
                 cell_size,
                 cell_size,
-                &format!("{}{}", (b'A' + 7 - col as u8) as char, row + 1) as &str,
+                BoardPosition::from_idx(row, col).to_string().as_str(),
             );
 
             but.set_color(if (row + col) % 2 == 0 {
@@ -70,18 +100,28 @@ impl GameWindow {
             let game_state = game_state.clone();
             but.set_callback(move |_but| {
                 if let Ok(mut game_state) = game_state.write() {
+                    if game_state.result.is_some() {
+                        return;
+                    }
                     let clicked_pos = BoardPosition::from_idx(row as usize, col as usize);
-                    if game_state.position_from.is_some_and(|from| {
-                        game_state
-                            .board
-                            .move_piece(&from, &BoardPosition::from_idx(row as usize, col as usize))
-                    }) {
-                        game_state.current_player = !game_state.current_player;
+                    let chess_move = game_state.position_from.and_then(|from| {
+                        if game_state.game.board().is_promotion(&from, &clicked_pos) {
+                            Self::pick_promotion()
+                                .map(|piece| Move::promoting(from, clicked_pos, piece))
+                        } else {
+                            Some(Move::new(from, clicked_pos))
+                        }
+                    });
+                    if chess_move.is_some_and(|chess_move| game_state.game.play_move(&chess_move)) {
                         game_state.needs_redraw = true;
+                        game_state.result = game_state.game.result();
                     } else if game_state
-                        .board
+                        .game
+                        .board()
                         .get_piece_at_position(&clicked_pos)
-                        .is_some_and(|(_piece, color)| color == game_state.current_player)
+                        .is_some_and(|(_piece, color)| {
+                            color == game_state.game.board().current_player
+                        })
                     {
                         game_state.position_from = Some(clicked_pos)
                     }
@@ -90,4 +130,47 @@ impl GameWindow {
             (but, (row, col))
         })
     }
+
+    /// Lets the player choose what a pawn turns into, `None` if the menu was dismissed
+    fn pick_promotion() -> Option<ChessPiece> {
+        let choices = MenuItem::new(&["Queen", "Rook", "Bishop", "Knight"]);
+        choices
+            .popup(app::event_x(), app::event_y())
+            .and_then(|item| item.label())
+            .and_then(|label| match label.as_str() {
+                "Queen" => Some(ChessPiece::Queen),
+                "Rook" => Some(ChessPiece::Rook),
+                "Bishop" => Some(ChessPiece::Bishoph),
+                "Knight" => Some(ChessPiece::Knight),
+                _ => None,
+            })
+    }
+
+    fn save_pgn(game_state: &GameState) {
+        if let Some(path) = dialog::file_chooser("Save game as PGN", "*.pgn", ".", false) {
+            let pgn = PgnGame::from_game(&game_state.game).to_pgn();
+            if let Err(error) = std::fs::write(&path, pgn) {
+                dialog::alert_default(&format!("Could not save the game: {error}"));
+            }
+        }
+    }
+
+    fn load_pgn(game_state: &mut GameState) {
+        let Some(path) = dialog::file_chooser("Load game from PGN", "*.pgn", ".", false) else {
+            return;
+        };
+        let game = std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| PgnGame::parse(&text).map_err(|error| error.to_string()))
+            .and_then(|pgn| pgn.replay().map_err(|error| error.to_string()));
+        match game {
+            Ok(game) => {
+                game_state.result = game.result();
+                game_state.game = game;
+                game_state.position_from = None;
+                game_state.needs_redraw = true;
+            }
+            Err(error) => dialog::alert_default(&format!("Could not load the game: {error}")),
+        }
+    }
 }
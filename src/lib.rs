@@ -1,6 +1,8 @@
 mod algorythm;
 mod chess_logic;
+mod engine;
 pub mod gui;
 pub use chess_logic::*;
 
 pub use algorythm::*;
+pub use engine::*;